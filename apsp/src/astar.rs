@@ -0,0 +1,321 @@
+use std::collections::BinaryHeap;
+
+use Graph;
+use dijkstra;
+use dijkstra::State;
+
+// A lower bound on the distance from a vertex to the target. A* only returns shortest
+// paths if the estimate is consistent: estimate(u) <= w(u, v) + estimate(v) for every edge.
+pub trait Heuristic {
+    fn estimate(&self, v: usize, target: usize) -> i32;
+}
+
+// No estimate at all, which makes A* a plain Dijkstra.
+pub struct Zero;
+
+impl Heuristic for Zero {
+    fn estimate(&self, _: usize, _: usize) -> i32 {
+        0
+    }
+}
+
+impl<F: Fn(usize, usize) -> i32> Heuristic for F {
+    fn estimate(&self, v: usize, target: usize) -> i32 {
+        self(v, target)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Search {
+    pub path: Vec<usize>,
+    pub cost: i32,
+    pub settled: usize,
+}
+
+// A* from source to target over non-negative weights. Graphs with negative edges can be
+// searched after dijkstra::reweight. Returns None if the target is unreachable.
+pub fn astar<H: Heuristic>(g: &Graph, source: usize, target: usize, heuristic: &H) -> Option<Search> {
+    let len = g.vertices.len();
+    let mut dist = vec![std::i32::MAX; len];
+    let mut parent: Vec<usize> = (0..len).collect();
+    let mut settled = vec![false; len];
+    let mut count = 0;
+    let mut heap = BinaryHeap::new();
+
+    dist[source] = 0;
+    heap.push(State{cost: heuristic.estimate(source, target), vertex: source});
+
+    while let Some(State{vertex, ..}) = heap.pop() {
+        if settled[vertex] {
+            continue;
+        }
+
+        settled[vertex] = true;
+        count += 1;
+
+        if vertex == target {
+            let mut path = vec![target];
+            let mut current = target;
+
+            while current != source {
+                current = parent[current];
+                path.push(current);
+            }
+
+            path.reverse();
+
+            return Some(Search{path: path, cost: dist[target], settled: count});
+        }
+
+        for e in &g.vertices[vertex].edges {
+            let next = dist[vertex] + e.weight;
+
+            if !settled[e.tail] && next < dist[e.tail] {
+                dist[e.tail] = next;
+                parent[e.tail] = vertex;
+                heap.push(State{cost: next + heuristic.estimate(e.tail, target), vertex: e.tail});
+            }
+        }
+    }
+
+    None
+}
+
+// ALT: A*, landmarks and the triangle inequality. For any landmark l,
+// d(v, t) >= d(l, t) - d(l, v) and d(v, t) >= d(v, l) - d(t, l).
+#[derive(Debug, Clone)]
+pub struct Landmarks {
+    landmarks: Vec<usize>,
+    from: Vec<Vec<i32>>,
+    to: Vec<Vec<i32>>,
+}
+
+impl Landmarks {
+    // Precomputes distances to and from the given landmarks. Weights must be non-negative.
+    pub fn with(g: &Graph, landmarks: Vec<usize>) -> Landmarks {
+        let r = g.reversed();
+        let from = landmarks.iter().map(|&l| dijkstra::dijkstra(g, l)).collect();
+        let to = landmarks.iter().map(|&l| dijkstra::dijkstra(&r, l)).collect();
+
+        Landmarks{landmarks: landmarks, from: from, to: to}
+    }
+
+    // Farthest selection: each landmark is the vertex farthest from those already chosen,
+    // starting from the vertex farthest from vertex 0.
+    pub fn farthest(g: &Graph, count: usize) -> Landmarks {
+        let len = g.vertices.len();
+        let mut chosen: Vec<usize> = vec![];
+
+        if len == 0 {
+            return Landmarks::with(g, chosen);
+        }
+
+        let mut nearest = dijkstra::dijkstra(g, 0);
+
+        while chosen.len() < count && chosen.len() < len {
+            let mut best: Option<usize> = None;
+
+            for v in 0..len {
+                if chosen.contains(&v) {
+                    continue;
+                }
+
+                // Prefer reachable vertices, so landmarks land in the same part of the graph.
+                let better = match best {
+                    None => true,
+                    Some(b) => {
+                        let reach = |d: i32| if d == std::i32::MAX { -1 } else { d };
+
+                        reach(nearest[v]) > reach(nearest[b])
+                    },
+                };
+
+                if better {
+                    best = Some(v);
+                }
+            }
+
+            let l = best.unwrap();
+            let dist = dijkstra::dijkstra(g, l);
+
+            chosen.push(l);
+
+            if chosen.len() == 1 {
+                nearest = dist;
+            } else {
+                for v in 0..len {
+                    nearest[v] = std::cmp::min(nearest[v], dist[v]);
+                }
+            }
+
+            for &c in &chosen {
+                nearest[c] = std::i32::MAX;
+            }
+        }
+
+        Landmarks::with(g, chosen)
+    }
+
+    pub fn landmarks(&self) -> &[usize] {
+        &self.landmarks
+    }
+}
+
+impl Heuristic for Landmarks {
+    fn estimate(&self, v: usize, target: usize) -> i32 {
+        let inf = std::i32::MAX;
+        let mut bound = 0;
+
+        for i in 0..self.landmarks.len() {
+            let (lv, lt) = (self.from[i][v], self.from[i][target]);
+            let (vl, tl) = (self.to[i][v], self.to[i][target]);
+
+            if lv != inf && lt != inf && lt - lv > bound {
+                bound = lt - lv;
+            }
+
+            if vl != inf && tl != inf && vl - tl > bound {
+                bound = vl - tl;
+            }
+        }
+
+        bound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::Instant;
+    use Graph;
+    use dijkstra;
+
+    fn pairs(len: usize) -> Vec<(usize, usize)> {
+        (0..40).map(|i| ((i * 37) % len, (i * 101 + 13) % len)).collect()
+    }
+
+    #[test]
+    fn grid() {
+        let mut g = Graph::new();
+
+        g.build(9);
+
+        // A 3x3 grid with edges both ways.
+        for r in 0..3 {
+            for c in 0..3 {
+                let v = r * 3 + c;
+
+                if c < 2 {
+                    g.add_edge(v, v + 1, 1);
+                    g.add_edge(v + 1, v, 1);
+                }
+
+                if r < 2 {
+                    g.add_edge(v, v + 3, 1);
+                    g.add_edge(v + 3, v, 1);
+                }
+            }
+        }
+
+        let manhattan = |v: usize, t: usize| {
+            ((v / 3) as i32 - (t / 3) as i32).abs() + ((v % 3) as i32 - (t % 3) as i32).abs()
+        };
+
+        let plain = astar(&g, 0, 2, &Zero).unwrap();
+        let guided = astar(&g, 0, 2, &manhattan).unwrap();
+
+        assert_eq!(plain.cost, 2);
+        assert_eq!(guided, Search{path: vec![0, 1, 2], cost: 2, settled: 3});
+        assert!(guided.settled < plain.settled);
+        assert_eq!(astar(&g, 0, 8, &Landmarks::farthest(&g, 2)).unwrap().cost, 4);
+    }
+
+    #[test]
+    fn landmarks_bound() {
+        let g = Graph::from_file(Path::new("g3.txt")).ok().unwrap();
+        let (r, _) = dijkstra::reweight(&g).ok().unwrap();
+        let alt = Landmarks::farthest(&r, 4);
+
+        assert_eq!(alt.landmarks().len(), 4);
+
+        for (s, t) in pairs(r.vertices.len()) {
+            let exact = dijkstra::dijkstra(&r, s)[t];
+
+            if exact != std::i32::MAX {
+                assert!(alt.estimate(s, t) <= exact);
+            }
+        }
+    }
+
+    #[test]
+    fn alt_matches_dijkstra() {
+        let g = Graph::from_file(Path::new("g3.txt")).ok().unwrap();
+        let (r, h) = dijkstra::reweight(&g).ok().unwrap();
+        let alt = Landmarks::farthest(&r, 8);
+        let (mut plain_settled, mut alt_settled) = (0, 0);
+
+        for (s, t) in pairs(r.vertices.len()) {
+            let plain = astar(&r, s, t, &Zero);
+            let guided = astar(&r, s, t, &alt);
+
+            assert_eq!(plain.as_ref().map(|p| p.cost), guided.as_ref().map(|p| p.cost));
+
+            if let (Some(p), Some(a)) = (plain, guided) {
+                let original = dijkstra::bellman_ford(&g, s).ok().unwrap()[t];
+
+                assert_eq!(a.cost - h[s] + h[t], original);
+
+                plain_settled += p.settled;
+                alt_settled += a.settled;
+            }
+        }
+
+        assert!(alt_settled < plain_settled);
+    }
+
+    // cargo test --release -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn benchmark() {
+        for file in &["g1.txt", "g2.txt", "g3.txt"] {
+            let g = Graph::from_file(Path::new(file)).ok().unwrap();
+            let r = match dijkstra::reweight(&g) {
+                Ok((r, _)) => r,
+                Err(_) => {
+                    // Negative cycles; benchmark on the absolute weights instead.
+                    let mut r = Graph::new();
+
+                    r.build(g.vertices.len() as i32);
+
+                    for v in &g.vertices {
+                        for e in &v.edges {
+                            r.add_edge(v.label as usize, e.tail, e.weight.abs());
+                        }
+                    }
+
+                    r
+                },
+            };
+
+            let start = Instant::now();
+            let alt = Landmarks::farthest(&r, 16);
+            let preprocessing = start.elapsed();
+
+            for &(name, use_alt) in &[("dijkstra", false), ("alt", true)] {
+                let start = Instant::now();
+                let mut settled = 0;
+
+                for (s, t) in pairs(r.vertices.len()) {
+                    let found = if use_alt { astar(&r, s, t, &alt) } else { astar(&r, s, t, &Zero) };
+
+                    settled += found.map_or(0, |f| f.settled);
+                }
+
+                println!("{} {}: {:?} for 40 queries, {} settled", file, name, start.elapsed(), settled);
+            }
+
+            println!("{} alt preprocessing: {:?}", file, preprocessing);
+        }
+    }
+}
@@ -0,0 +1,496 @@
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+
+use Graph;
+use dijkstra;
+use dijkstra::State;
+
+// Settled vertices after which a witness search gives up. Giving up early only costs an
+// unnecessary shortcut, never a wrong distance.
+const WITNESS_LIMIT: usize = 50;
+
+// Contraction hierarchy. Vertices are contracted one at a time in order of edge
+// difference, adding a shortcut u -> w whenever the only shortest u -> w path ran through
+// the contracted vertex. Queries then only ever move upwards in the order, from both ends.
+#[derive(Debug, Clone)]
+pub struct ContractionHierarchy {
+    rank: Vec<usize>,
+    up: Vec<Vec<(usize, i32)>>,
+    down: Vec<Vec<(usize, i32)>>,
+    // Every edge and shortcut, with the vertex a shortcut bypasses.
+    edges: HashMap<(usize, usize), (i32, Option<usize>)>,
+    shortcuts: usize,
+    h: Vec<i32>,
+}
+
+struct Remaining {
+    out: Vec<HashMap<usize, i32>>,
+    inc: Vec<HashMap<usize, i32>>,
+    contracted: Vec<bool>,
+}
+
+impl Remaining {
+    // Distances from source to each target that avoid `skip`, up to `limit`.
+    fn witness(&self, source: usize, skip: usize, targets: &HashMap<usize, i32>, limit: i32) -> HashMap<usize, i32> {
+        let mut dist: HashMap<usize, i32> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        let mut settled = 0;
+        let mut found = 0;
+
+        dist.insert(source, 0);
+        heap.push(State{cost: 0, vertex: source});
+
+        while let Some(State{cost, vertex}) = heap.pop() {
+            if cost > dist[&vertex] {
+                continue;
+            }
+
+            if cost > limit || settled >= WITNESS_LIMIT || found == targets.len() {
+                break;
+            }
+
+            settled += 1;
+
+            if targets.contains_key(&vertex) {
+                found += 1;
+            }
+
+            for (&next, &w) in &self.out[vertex] {
+                if next == skip || self.contracted[next] {
+                    continue;
+                }
+
+                let d = cost + w;
+
+                if dist.get(&next).map_or(true, |&old| d < old) {
+                    dist.insert(next, d);
+                    heap.push(State{cost: d, vertex: next});
+                }
+            }
+        }
+
+        dist
+    }
+
+    // The shortcuts needed to contract v.
+    fn shortcuts(&self, v: usize) -> Vec<(usize, usize, i32)> {
+        let mut needed = vec![];
+        let targets: HashMap<usize, i32> = self.out[v].iter()
+                                                      .filter(|&(&w, _)| w != v && !self.contracted[w])
+                                                      .map(|(&w, &c)| (w, c))
+                                                      .collect();
+
+        if targets.is_empty() {
+            return needed;
+        }
+
+        let longest = *targets.values().max().unwrap();
+
+        for (&u, &to_v) in &self.inc[v] {
+            if u == v || self.contracted[u] {
+                continue;
+            }
+
+            let dist = self.witness(u, v, &targets, to_v + longest);
+
+            for (&w, &from_v) in &targets {
+                if w == u {
+                    continue;
+                }
+
+                let via = to_v + from_v;
+
+                if dist.get(&w).map_or(true, |&d| d > via) {
+                    needed.push((u, w, via));
+                }
+            }
+        }
+
+        needed
+    }
+
+    fn live_degree(&self, v: usize) -> usize {
+        self.out[v].keys().chain(self.inc[v].keys()).filter(|&&w| w != v && !self.contracted[w]).count()
+    }
+
+    fn priority(&self, v: usize, deleted: &[i64]) -> i64 {
+        self.shortcuts(v).len() as i64 - self.live_degree(v) as i64 + deleted[v]
+    }
+}
+
+fn relax(map: &mut HashMap<usize, i32>, key: usize, w: i32) {
+    let entry = map.entry(key).or_insert(w);
+
+    if w < *entry {
+        *entry = w;
+    }
+}
+
+impl ContractionHierarchy {
+    // Preprocesses the graph. Negative edges are handled by Johnson's reweighting, so only
+    // a negative cycle is an error.
+    pub fn new(g: &Graph) -> Result<ContractionHierarchy, &'static str> {
+        let len = g.vertices.len();
+        let negative = g.vertices.iter().any(|v| v.edges.iter().any(|e| e.weight < 0));
+        let h = if negative { try!(dijkstra::potentials(g)) } else { vec![0; len] };
+        let mut remaining = Remaining{out: vec![HashMap::new(); len],
+                                      inc: vec![HashMap::new(); len],
+                                      contracted: vec![false; len]};
+        let mut edges: HashMap<(usize, usize), (i32, Option<usize>)> = HashMap::new();
+        let mut shortcuts = 0;
+
+        for v in &g.vertices {
+            let head = v.label as usize;
+
+            for e in &v.edges {
+                if e.tail == head {
+                    continue;
+                }
+
+                let w = e.weight + h[head] - h[e.tail];
+
+                relax(&mut remaining.out[head], e.tail, w);
+                relax(&mut remaining.inc[e.tail], head, w);
+            }
+        }
+
+        for (u, out) in remaining.out.iter().enumerate() {
+            for (&w, &c) in out {
+                edges.insert((u, w), (c, None));
+            }
+        }
+
+        // Lazy priority queue on edge difference, smallest first.
+        let mut deleted = vec![0; len];
+        let mut queue = BinaryHeap::new();
+        let mut rank = vec![0; len];
+
+        for v in 0..len {
+            queue.push(Priority{value: remaining.priority(v, &deleted), vertex: v});
+        }
+
+        let mut order = 0;
+
+        while let Some(Priority{value, vertex}) = queue.pop() {
+            if remaining.contracted[vertex] {
+                continue;
+            }
+
+            let current = remaining.priority(vertex, &deleted);
+
+            if current > value {
+                if let Some(next) = queue.peek() {
+                    if current > next.value {
+                        queue.push(Priority{value: current, vertex: vertex});
+                        continue;
+                    }
+                }
+            }
+
+            for (u, w, c) in remaining.shortcuts(vertex) {
+                let better = edges.get(&(u, w)).map_or(true, |&(old, _)| c < old);
+
+                if better {
+                    edges.insert((u, w), (c, Some(vertex)));
+                    relax(&mut remaining.out[u], w, c);
+                    relax(&mut remaining.inc[w], u, c);
+                    shortcuts += 1;
+                }
+            }
+
+            remaining.contracted[vertex] = true;
+            rank[vertex] = order;
+            order += 1;
+
+            let neighbours: Vec<usize> = remaining.out[vertex].keys().chain(remaining.inc[vertex].keys()).cloned().collect();
+
+            for w in neighbours {
+                deleted[w] += 1;
+            }
+        }
+
+        let mut up = vec![vec![]; len];
+        let mut down = vec![vec![]; len];
+
+        for (&(u, w), &(c, _)) in &edges {
+            if rank[w] > rank[u] {
+                up[u].push((w, c));
+            } else {
+                down[w].push((u, c));
+            }
+        }
+
+        Ok(ContractionHierarchy{rank: rank, up: up, down: down, edges: edges, shortcuts: shortcuts, h: h})
+    }
+
+    pub fn shortcuts(&self) -> usize {
+        self.shortcuts
+    }
+
+    // Position of the vertex in the contraction order.
+    pub fn rank(&self, v: usize) -> usize {
+        self.rank[v]
+    }
+
+    pub fn distance(&self, source: usize, target: usize) -> Option<i32> {
+        self.search(source, target).map(|(_, _, _, d)| d)
+    }
+
+    // The shortest path with every shortcut unpacked into original edges.
+    pub fn path(&self, source: usize, target: usize) -> Option<(Vec<usize>, i32)> {
+        let (meet, forward, backward, d) = match self.search(source, target) {
+            Some(found) => found,
+            None => return None,
+        };
+
+        let mut hops = vec![meet];
+        let mut current = meet;
+
+        while current != source {
+            current = forward[&current];
+            hops.push(current);
+        }
+
+        hops.reverse();
+        current = meet;
+
+        while current != target {
+            current = backward[&current];
+            hops.push(current);
+        }
+
+        let mut path = vec![source];
+
+        for pair in hops.windows(2) {
+            self.unpack(pair[0], pair[1], &mut path);
+        }
+
+        Some((path, d))
+    }
+
+    // Appends the original vertices of edge (u, w), except u itself.
+    fn unpack(&self, u: usize, w: usize, path: &mut Vec<usize>) {
+        let mut stack = vec![(u, w)];
+
+        while let Some((a, b)) = stack.pop() {
+            match self.edges[&(a, b)].1 {
+                Some(middle) => {
+                    stack.push((middle, b));
+                    stack.push((a, middle));
+                },
+                None => path.push(b),
+            }
+        }
+    }
+
+    // Bidirectional upward Dijkstra. Returns the meeting vertex, both parent maps and the
+    // distance in terms of the original weights.
+    fn search(&self, source: usize, target: usize) -> Option<(usize, HashMap<usize, usize>, HashMap<usize, usize>, i32)> {
+        let mut dist = [HashMap::new(), HashMap::new()];
+        let mut parent = [HashMap::new(), HashMap::new()];
+        let mut heap = [BinaryHeap::new(), BinaryHeap::new()];
+        let mut best: Option<(i32, usize)> = None;
+
+        dist[0].insert(source, 0);
+        dist[1].insert(target, 0);
+        heap[0].push(State{cost: 0, vertex: source});
+        heap[1].push(State{cost: 0, vertex: target});
+
+        loop {
+            let bound = best.map_or(std::i32::MAX, |(b, _)| b);
+            let side = match (heap[0].peek(), heap[1].peek()) {
+                (Some(f), Some(b)) => if f.cost <= b.cost { 0 } else { 1 },
+                (Some(_), None) => 0,
+                (None, Some(_)) => 1,
+                (None, None) => break,
+            };
+
+            let State{cost, vertex} = heap[side].pop().unwrap();
+
+            if cost >= bound {
+                // Everything left on this side is at least as far.
+                heap[side].clear();
+                continue;
+            }
+
+            if cost > dist[side][&vertex] {
+                continue;
+            }
+
+            if let Some(&other) = dist[1 - side].get(&vertex) {
+                if cost + other < bound {
+                    best = Some((cost + other, vertex));
+                }
+            }
+
+            let edges = if side == 0 { &self.up[vertex] } else { &self.down[vertex] };
+
+            for &(next, w) in edges {
+                let d = cost + w;
+
+                if dist[side].get(&next).map_or(true, |&old| d < old) {
+                    dist[side].insert(next, d);
+                    parent[side].insert(next, vertex);
+                    heap[side].push(State{cost: d, vertex: next});
+                }
+            }
+        }
+
+        let (d, meet) = match best {
+            Some(found) => found,
+            None => return None,
+        };
+
+        let [forward, backward] = parent;
+
+        Some((meet, forward, backward, d - self.h[source] + self.h[target]))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Priority {
+    value: i64,
+    vertex: usize,
+}
+
+// Reversed so that BinaryHeap behaves as a min-heap on value.
+impl Ord for Priority {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.value.cmp(&self.value)
+            .then_with(|| other.vertex.cmp(&self.vertex))
+    }
+}
+
+impl PartialOrd for Priority {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::Instant;
+    use Graph;
+    use dijkstra;
+    use paths;
+    use rng::Rng;
+
+    fn check(g: &Graph, ch: &ContractionHierarchy, source: usize) {
+        let exact = dijkstra::bellman_ford(g, source).ok().unwrap();
+
+        for t in 0..g.vertices.len() {
+            if exact[t] == std::i32::MAX {
+                assert_eq!(ch.distance(source, t), None);
+                continue;
+            }
+
+            let (path, d) = ch.path(source, t).unwrap();
+
+            assert_eq!(d, exact[t]);
+            assert_eq!(path[0], source);
+            assert_eq!(path[path.len() - 1], t);
+            assert_eq!(paths::path_cost(g, &path), Some(d));
+        }
+    }
+
+    #[test]
+    fn simple1() {
+        let g = Graph::from_file(Path::new("g_simple1.txt")).ok().unwrap();
+        let ch = ContractionHierarchy::new(&g).ok().unwrap();
+
+        for s in 0..g.vertices.len() {
+            check(&g, &ch, s);
+        }
+    }
+
+    #[test]
+    fn negative_cycle() {
+        let g = Graph::from_file(Path::new("g_simple2.txt")).ok().unwrap();
+
+        assert!(ContractionHierarchy::new(&g).is_err());
+    }
+
+    #[test]
+    fn line() {
+        let mut g = Graph::new();
+
+        g.build(5);
+
+        for v in 0..4 {
+            g.add_edge(v, v + 1, 2);
+            g.add_edge(v + 1, v, 3);
+        }
+
+        let ch = ContractionHierarchy::new(&g).ok().unwrap();
+
+        assert_eq!(ch.path(0, 4), Some((vec![0, 1, 2, 3, 4], 8)));
+        assert_eq!(ch.distance(4, 0), Some(12));
+        assert_eq!(ch.distance(2, 2), Some(0));
+    }
+
+    // A road-like 20x20 grid with varying weights in each direction.
+    fn grid() -> Graph {
+        let mut g = Graph::new();
+        let mut rng = Rng::new(11);
+        let mut weight = || 1 + rng.next() as i32 % 20;
+
+        g.build(400);
+
+        for r in 0..20 {
+            for c in 0..20 {
+                let v = r * 20 + c;
+
+                if c < 19 {
+                    g.add_edge(v, v + 1, weight());
+                    g.add_edge(v + 1, v, weight());
+                }
+
+                if r < 19 {
+                    g.add_edge(v, v + 20, weight());
+                    g.add_edge(v + 20, v, weight());
+                }
+            }
+        }
+
+        g
+    }
+
+    #[test]
+    fn road_grid() {
+        let g = grid();
+        let ch = ContractionHierarchy::new(&g).ok().unwrap();
+
+        for &s in &[0, 19, 210, 399] {
+            check(&g, &ch, s);
+        }
+
+        assert!(ch.rank(0) < g.vertices.len());
+    }
+
+    // cargo test --release -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn benchmark() {
+        for file in &["g3.txt"] {
+            let g = Graph::from_file(Path::new(file)).ok().unwrap();
+            let start = Instant::now();
+            let ch = ContractionHierarchy::new(&g).ok().unwrap();
+
+            println!("{} preprocessing: {:?}, {} shortcuts", file, start.elapsed(), ch.shortcuts());
+
+            let start = Instant::now();
+
+            for s in 0..100 {
+                for t in 0..g.vertices.len() {
+                    ch.distance(s, t);
+                }
+            }
+
+            println!("{} queries from 100 sources: {:?}", file, start.elapsed());
+
+            check(&g, &ch, 0);
+        }
+    }
+}
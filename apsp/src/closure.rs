@@ -0,0 +1,193 @@
+use Graph;
+use scc;
+
+// A square matrix of bits, each row packed into 64-bit words.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitMatrix {
+    size: usize,
+    words: usize,
+    bits: Vec<u64>,
+}
+
+impl BitMatrix {
+    pub fn new(size: usize) -> BitMatrix {
+        let words = (size + 63) / 64;
+
+        BitMatrix{size: size, words: words, bits: vec![0; size * words]}
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> bool {
+        self.bits[row * self.words + col / 64] & (1 << (col % 64)) != 0
+    }
+
+    pub fn set(&mut self, row: usize, col: usize) {
+        self.bits[row * self.words + col / 64] |= 1 << (col % 64);
+    }
+
+    // Ors row `from` into row `into`, a word at a time.
+    pub fn union_rows(&mut self, into: usize, from: usize) {
+        if into == from {
+            return;
+        }
+
+        let words = self.words;
+
+        for w in 0..words {
+            let bits = self.bits[from * words + w];
+
+            self.bits[into * words + w] |= bits;
+        }
+    }
+
+    pub fn count_row(&self, row: usize) -> usize {
+        self.bits[(row * self.words)..((row + 1) * self.words)].iter()
+                                                               .map(|w| w.count_ones() as usize)
+                                                               .sum()
+    }
+}
+
+// Warshall's algorithm over bit rows, O(n³/64). Every vertex reaches itself.
+pub fn transitive_closure(g: &Graph) -> BitMatrix {
+    let len = g.vertices.len();
+    let mut m = BitMatrix::new(len);
+
+    for v in &g.vertices {
+        m.set(v.label as usize, v.label as usize);
+
+        for e in &v.edges {
+            m.set(v.label as usize, e.tail);
+        }
+    }
+
+    for k in 0..len {
+        for i in 0..len {
+            if m.get(i, k) {
+                m.union_rows(i, k);
+            }
+        }
+    }
+
+    m
+}
+
+// Reachability between strongly connected components. Every vertex in a component reaches
+// the same set, so the closure is only computed over the condensed DAG.
+#[derive(Debug, Clone)]
+pub struct Reachability {
+    component: Vec<usize>,
+    reach: BitMatrix,
+}
+
+impl Reachability {
+    pub fn new(g: &Graph) -> Reachability {
+        let (count, component) = scc::tarjan(g);
+        let mut successors: Vec<Vec<usize>> = vec![vec![]; count];
+        let mut reach = BitMatrix::new(count);
+
+        for v in &g.vertices {
+            let c = component[v.label as usize];
+
+            for e in &v.edges {
+                if component[e.tail] != c {
+                    successors[c].push(component[e.tail]);
+                }
+            }
+        }
+
+        // Components are numbered in reverse topological order, so successors come first.
+        for c in 0..count {
+            reach.set(c, c);
+
+            for &d in &successors[c] {
+                if !reach.get(c, d) {
+                    reach.union_rows(c, d);
+                }
+            }
+        }
+
+        Reachability{component: component, reach: reach}
+    }
+
+    pub fn reachable(&self, from: usize, to: usize) -> bool {
+        self.reach.get(self.component[from], self.component[to])
+    }
+
+    pub fn components(&self) -> usize {
+        self.reach.size()
+    }
+
+    pub fn component(&self, v: usize) -> usize {
+        self.component[v]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use Graph;
+
+    #[test]
+    fn bit_matrix() {
+        let mut m = BitMatrix::new(130);
+
+        m.set(0, 129);
+        m.set(1, 64);
+        m.union_rows(1, 0);
+
+        assert!(m.get(1, 129));
+        assert!(m.get(1, 64));
+        assert!(!m.get(0, 64));
+        assert_eq!(m.count_row(1), 2);
+    }
+
+    #[test]
+    fn simple1() {
+        let g = Graph::from_file(Path::new("g_simple1.txt")).ok().unwrap();
+        let m = transitive_closure(&g);
+
+        assert!(m.get(0, 4));
+        assert!(m.get(2, 1));
+        assert!(!m.get(0, 5));
+        assert!(!m.get(3, 0));
+        assert!(m.get(5, 5));
+        assert_eq!(m.count_row(0), 5);
+    }
+
+    #[test]
+    fn index_matches_closure() {
+        let g = Graph::from_file(Path::new("g1.txt")).ok().unwrap();
+        let m = transitive_closure(&g);
+        let r = Reachability::new(&g);
+
+        for u in 0..g.vertices.len() {
+            for v in 0..g.vertices.len() {
+                assert_eq!(r.reachable(u, v), m.get(u, v));
+            }
+        }
+    }
+
+    #[test]
+    fn condensed() {
+        let mut g = Graph::new();
+
+        g.build(5);
+
+        for &(h, t) in &[(0, 1), (1, 0), (1, 2), (3, 4)] {
+            g.add_edge(h, t, 0);
+        }
+
+        let r = Reachability::new(&g);
+
+        assert_eq!(r.components(), 4);
+        assert_eq!(r.component(0), r.component(1));
+        assert!(r.reachable(1, 2));
+        assert!(r.reachable(0, 0));
+        assert!(!r.reachable(2, 0));
+        assert!(!r.reachable(0, 3));
+    }
+}
@@ -0,0 +1,366 @@
+use std::cmp::Ordering;
+
+use Graph;
+use dijkstra;
+
+// A directed cycle. The last vertex has an edge back to the first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cycle {
+    pub vertices: Vec<usize>,
+    pub weight: i32,
+}
+
+impl Cycle {
+    pub fn mean(&self) -> f64 {
+        self.weight as f64 / self.vertices.len() as f64
+    }
+}
+
+// Compares a/b with c/d for positive b and d.
+fn cmp_ratio(a: i64, b: i64, c: i64, d: i64) -> Ordering {
+    (a * d).cmp(&(c * b))
+}
+
+// Karp's minimum mean cycle in O(nm). Returns None if the graph is acyclic.
+pub fn min_mean_cycle(g: &Graph) -> Option<Cycle> {
+    let len = g.vertices.len();
+    let inf = std::i64::MAX;
+
+    // d[k][v] is the lightest walk of exactly k edges ending at v, starting anywhere.
+    let mut d: Vec<Vec<i64>> = vec![vec![inf; len]; len + 1];
+    let mut parent: Vec<Vec<usize>> = vec![vec![0; len]; len + 1];
+
+    for v in 0..len {
+        d[0][v] = 0;
+    }
+
+    for k in 1..(len + 1) {
+        for v in &g.vertices {
+            let head = v.label as usize;
+
+            if d[k - 1][head] == inf {
+                continue;
+            }
+
+            for e in &v.edges {
+                let w = d[k - 1][head] + e.weight as i64;
+
+                if w < d[k][e.tail] {
+                    d[k][e.tail] = w;
+                    parent[k][e.tail] = head;
+                }
+            }
+        }
+    }
+
+    // The minimum mean is min over v of max over k of (d[n][v] - d[k][v]) / (n - k).
+    let mut best: Option<(usize, i64, i64)> = None;
+
+    for v in 0..len {
+        if d[len][v] == inf {
+            continue;
+        }
+
+        let mut worst: Option<(i64, i64)> = None;
+
+        for k in 0..len {
+            if d[k][v] == inf {
+                continue;
+            }
+
+            let (num, den) = (d[len][v] - d[k][v], (len - k) as i64);
+
+            worst = match worst {
+                Some((n, m)) if cmp_ratio(n, m, num, den) != Ordering::Less => Some((n, m)),
+                _ => Some((num, den)),
+            };
+        }
+
+        if let Some((num, den)) = worst {
+            best = match best {
+                Some((b, n, m)) if cmp_ratio(n, m, num, den) != Ordering::Greater => Some((b, n, m)),
+                _ => Some((v, num, den)),
+            };
+        }
+    }
+
+    let v = match best {
+        Some((v, _, _)) => v,
+        None => return None,
+    };
+
+    // Walk back n edges from v. Any repeated vertex on that walk closes a cycle of minimum mean.
+    let mut walk = vec![v];
+    let mut current = v;
+
+    for k in (1..(len + 1)).rev() {
+        current = parent[k][current];
+        walk.push(current);
+    }
+
+    let mut first_seen = vec![None; len];
+
+    for (i, &u) in walk.iter().enumerate() {
+        if let Some(j) = first_seen[u] {
+            let mut vertices: Vec<usize> = walk[j..i].to_vec();
+            let mut weight = 0;
+
+            // Weights of the steps walk[i] -> walk[i - 1] are differences along d.
+            for s in (j + 1)..(i + 1) {
+                let k = len - s + 1;
+
+                weight += d[k][walk[s - 1]] - d[k - 1][walk[s]];
+            }
+
+            vertices.reverse();
+
+            return Some(Cycle{vertices: vertices, weight: weight as i32});
+        }
+
+        first_seen[u] = Some(i);
+    }
+
+    None
+}
+
+// A negative weight cycle anywhere in the graph, found with Bellman-Ford from a virtual
+// source. Returns None if there is no negative cycle.
+pub fn negative_cycle(g: &Graph) -> Option<Cycle> {
+    let len = g.vertices.len();
+    let mut dist = vec![0; len];
+    let mut parent: Vec<(usize, i32)> = (0..len).map(|v| (v, 0)).collect();
+    let mut last = None;
+
+    for _ in 0..(len + 1) {
+        last = None;
+
+        for v in &g.vertices {
+            let head = v.label as usize;
+
+            for e in &v.edges {
+                if dist[head] + e.weight < dist[e.tail] {
+                    dist[e.tail] = dist[head] + e.weight;
+                    parent[e.tail] = (head, e.weight);
+                    last = Some(e.tail);
+                }
+            }
+        }
+
+        if last.is_none() {
+            return None;
+        }
+    }
+
+    // Stepping back n times from a vertex relaxed in the final pass lands on the cycle.
+    let mut start = last.unwrap();
+
+    for _ in 0..len {
+        start = parent[start].0;
+    }
+
+    let mut vertices = vec![start];
+    let mut weight = parent[start].1;
+    let mut current = parent[start].0;
+
+    while current != start {
+        vertices.push(current);
+        weight += parent[current].1;
+        current = parent[current].0;
+    }
+
+    vertices.reverse();
+
+    Some(Cycle{vertices: vertices, weight: weight})
+}
+
+// The lightest directed cycle. Each vertex runs a Dijkstra over Johnson reweighted edges
+// and closes the cycle with an edge back into it. Fails if the graph has a negative cycle,
+// where the lightest simple cycle is NP-hard; use negative_cycle instead.
+pub fn min_weight_cycle(g: &Graph) -> Result<Option<Cycle>, &'static str> {
+    let h = try!(dijkstra::potentials(g));
+    let len = g.vertices.len();
+    let mut incoming: Vec<Vec<(usize, i32)>> = vec![vec![]; len];
+    let mut best: Option<Cycle> = None;
+
+    for v in &g.vertices {
+        for e in &v.edges {
+            incoming[e.tail].push((v.label as usize, e.weight));
+        }
+    }
+
+    for s in 0..len {
+        if incoming[s].is_empty() {
+            continue;
+        }
+
+        let (dist, parent) = dijkstra::shortest_path_tree(g, s, &h);
+
+        for &(u, w) in &incoming[s] {
+            if dist[u] == std::i32::MAX {
+                continue;
+            }
+
+            let weight = dist[u] + w;
+
+            if best.as_ref().map_or(true, |b| weight < b.weight) {
+                let mut vertices = vec![u];
+                let mut current = u;
+
+                while current != s {
+                    current = parent[current];
+                    vertices.push(current);
+                }
+
+                vertices.reverse();
+
+                best = Some(Cycle{vertices: vertices, weight: weight});
+            }
+        }
+    }
+
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use Graph;
+    use rng::Rng;
+
+    fn graph(size: i32, edges: &[(usize, usize, i32)]) -> Graph {
+        let mut g = Graph::new();
+
+        g.build(size);
+
+        for &(h, t, w) in edges {
+            g.add_edge(h, t, w);
+        }
+
+        g
+    }
+
+    fn is_cycle(g: &Graph, c: &Cycle) -> bool {
+        let len = c.vertices.len();
+        let mut weight = 0;
+
+        for i in 0..len {
+            let (h, t) = (c.vertices[i], c.vertices[(i + 1) % len]);
+
+            match g.vertices[h].edges.iter().filter(|e| e.tail == t).map(|e| e.weight).min() {
+                Some(w) => weight += w,
+                None => return false,
+            }
+        }
+
+        weight == c.weight
+    }
+
+    // Every simple cycle by brute force, as (weight, length).
+    fn all_cycles(g: &Graph) -> Vec<(i32, usize)> {
+        fn extend(g: &Graph, start: usize, path: &mut Vec<usize>, weight: i32, out: &mut Vec<(i32, usize)>) {
+            let last = path[path.len() - 1];
+
+            for e in &g.vertices[last].edges {
+                if e.tail == start {
+                    out.push((weight + e.weight, path.len()));
+                } else if e.tail > start && !path.contains(&e.tail) {
+                    path.push(e.tail);
+                    extend(g, start, path, weight + e.weight, out);
+                    path.pop();
+                }
+            }
+        }
+
+        let mut out = vec![];
+
+        for s in 0..g.vertices.len() {
+            extend(g, s, &mut vec![s], 0, &mut out);
+        }
+
+        out
+    }
+
+    #[test]
+    fn mean_cycle() {
+        let g = graph(4, &[(0, 1, 1), (1, 0, 1), (1, 2, 3), (2, 3, -1), (3, 1, 1)]);
+        let c = min_mean_cycle(&g).unwrap();
+
+        assert!(is_cycle(&g, &c));
+        assert_eq!(c.weight, 2);
+        assert_eq!(c.vertices.len(), 2);
+        assert_eq!(c.mean(), 1.0);
+
+        assert!(min_mean_cycle(&graph(3, &[(0, 1, 1), (1, 2, 1)])).is_none());
+    }
+
+    #[test]
+    fn mean_cycle_brute_force() {
+        let mut rng = Rng::new(7);
+        let mut next = || rng.next() as i32;
+
+        for _ in 0..50 {
+            let size = 2 + (next() % 5) as usize;
+            let mut edges = vec![];
+
+            for h in 0..size {
+                for t in 0..size {
+                    if next() % 3 == 0 {
+                        edges.push((h, t, next() % 21 - 10));
+                    }
+                }
+            }
+
+            let g = graph(size as i32, &edges);
+            let cycles = all_cycles(&g);
+
+            match min_mean_cycle(&g) {
+                None => assert!(cycles.is_empty()),
+                Some(c) => {
+                    assert!(is_cycle(&g, &c));
+
+                    for &(w, l) in &cycles {
+                        assert!(c.weight as i64 * l as i64 <= w as i64 * c.vertices.len() as i64);
+                    }
+                }
+            }
+
+            if cycles.iter().all(|&(w, _)| w >= 0) {
+                let c = min_weight_cycle(&g).ok().unwrap();
+
+                assert!(negative_cycle(&g).is_none());
+                assert_eq!(c.as_ref().map(|c| c.weight), cycles.iter().map(|&(w, _)| w).min());
+                assert!(c.map_or(true, |c| is_cycle(&g, &c)));
+            } else {
+                let c = negative_cycle(&g).unwrap();
+
+                assert!(c.weight < 0);
+                assert!(is_cycle(&g, &c));
+                assert!(min_weight_cycle(&g).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn arbitrage() {
+        let g = Graph::from_file(Path::new("g_simple2.txt")).ok().unwrap();
+        let c = negative_cycle(&g).unwrap();
+
+        assert_eq!(c.weight, -1);
+        assert_eq!(c.vertices.len(), 3);
+        assert!(is_cycle(&g, &c));
+
+        let m = min_mean_cycle(&g).unwrap();
+
+        assert_eq!(m.weight, -1);
+    }
+
+    #[test]
+    fn cheapest_cycle() {
+        let g = Graph::from_file(Path::new("g_simple1.txt")).ok().unwrap();
+        let c = min_weight_cycle(&g).ok().unwrap().unwrap();
+
+        assert_eq!(c.weight, 1);
+        assert!(is_cycle(&g, &c));
+    }
+}
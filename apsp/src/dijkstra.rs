@@ -0,0 +1,281 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::collections::HashSet;
+
+use Graph;
+
+// A heap entry for the priority queue searches.
+#[derive(Debug, PartialEq, Eq)]
+pub struct State {
+    pub cost: i32,
+    pub vertex: usize,
+}
+
+// Reversed so that BinaryHeap behaves as a min-heap on cost.
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.cmp(&self.cost)
+            .then_with(|| other.vertex.cmp(&self.vertex))
+    }
+}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Single source shortest paths. Handles negative edges and reports negative cycles
+// reachable from the source.
+pub fn bellman_ford(g: &Graph, source: usize) -> Result<Vec<i32>, &'static str> {
+    let len = g.vertices.len();
+    let mut dist = vec![std::i32::MAX; len];
+
+    dist[source] = 0;
+
+    for i in 0..len {
+        let mut changed = false;
+
+        for v in &g.vertices {
+            let head = v.label as usize;
+
+            if dist[head] == std::i32::MAX {
+                continue;
+            }
+
+            for e in &v.edges {
+                if dist[head] + e.weight < dist[e.tail] {
+                    if i == len - 1 {
+                        return Err("Negative cycle");
+                    }
+
+                    dist[e.tail] = dist[head] + e.weight;
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    Ok(dist)
+}
+
+// Johnson's vertex potentials: shortest distances from a virtual source joined to every
+// vertex by a zero weight edge. Reweighting each edge (u, v) to w + h(u) - h(v) makes
+// every weight non-negative while preserving shortest paths.
+pub fn potentials(g: &Graph) -> Result<Vec<i32>, &'static str> {
+    let len = g.vertices.len();
+    let mut h = vec![0; len];
+
+    for i in 0..(len + 1) {
+        let mut changed = false;
+
+        for v in &g.vertices {
+            let head = v.label as usize;
+
+            for e in &v.edges {
+                if h[head] + e.weight < h[e.tail] {
+                    if i == len {
+                        return Err("Negative cycle");
+                    }
+
+                    h[e.tail] = h[head] + e.weight;
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    Ok(h)
+}
+
+// A copy of the graph with each edge (u, v) reweighted to w + h(u) - h(v) by Johnson's
+// potentials, along with the potentials. Every weight in the copy is non-negative and a
+// distance d' in it maps back to d' - h(source) + h(target).
+pub fn reweight(g: &Graph) -> Result<(Graph, Vec<i32>), &'static str> {
+    let h = try!(potentials(g));
+    let mut r = Graph::new();
+
+    r.build(g.vertices.len() as i32);
+
+    for v in &g.vertices {
+        let head = v.label as usize;
+
+        for e in &v.edges {
+            r.add_edge(head, e.tail, e.weight + h[head] - h[e.tail]);
+        }
+    }
+
+    Ok((r, h))
+}
+
+// Single source shortest paths over non-negative weights.
+pub fn dijkstra(g: &Graph, source: usize) -> Vec<i32> {
+    let zero = vec![0; g.vertices.len()];
+
+    search(g, source, None, &zero, &Exclusions::none(g.vertices.len())).0
+}
+
+// Shortest path between a pair of vertices over non-negative weights. Returns the vertex
+// sequence and its cost, or None if the target is unreachable.
+pub fn shortest_path(g: &Graph, source: usize, target: usize) -> Option<(Vec<usize>, i32)> {
+    let zero = vec![0; g.vertices.len()];
+
+    path_between(g, source, target, &zero, &Exclusions::none(g.vertices.len()))
+}
+
+// Distances and parent pointers from the source over the weights reduced by `h`. The
+// parent of the source and of any unreachable vertex is itself.
+pub fn shortest_path_tree(g: &Graph, source: usize, h: &[i32]) -> (Vec<i32>, Vec<usize>) {
+    search(g, source, None, h, &Exclusions::none(g.vertices.len()))
+}
+
+// Vertices and edges to ignore during a search.
+pub struct Exclusions {
+    pub vertices: Vec<bool>,
+    pub edges: HashSet<(usize, usize)>,
+}
+
+impl Exclusions {
+    pub fn none(size: usize) -> Exclusions {
+        Exclusions{vertices: vec![false; size], edges: HashSet::new()}
+    }
+}
+
+// Dijkstra over the weights reduced by `h`. The returned distances and path cost are in
+// terms of the original weights.
+pub fn path_between(g: &Graph, source: usize, target: usize, h: &[i32], ex: &Exclusions) -> Option<(Vec<usize>, i32)> {
+    let (dist, parent) = search(g, source, Some(target), h, ex);
+
+    if dist[target] == std::i32::MAX {
+        return None;
+    }
+
+    let mut path = vec![target];
+    let mut current = target;
+
+    while current != source {
+        current = parent[current];
+        path.push(current);
+    }
+
+    path.reverse();
+
+    Some((path, dist[target]))
+}
+
+fn search(g: &Graph, source: usize, target: Option<usize>, h: &[i32], ex: &Exclusions) -> (Vec<i32>, Vec<usize>) {
+    let len = g.vertices.len();
+    let mut reduced = vec![std::i32::MAX; len];
+    let mut parent: Vec<usize> = (0..len).collect();
+    let mut heap = BinaryHeap::new();
+
+    if ex.vertices[source] {
+        return (reduced, parent);
+    }
+
+    reduced[source] = 0;
+    heap.push(State{cost: 0, vertex: source});
+
+    while let Some(State{cost, vertex}) = heap.pop() {
+        if cost > reduced[vertex] {
+            continue;
+        }
+
+        if Some(vertex) == target {
+            break;
+        }
+
+        for e in &g.vertices[vertex].edges {
+            if ex.vertices[e.tail] || ex.edges.contains(&(vertex, e.tail)) {
+                continue;
+            }
+
+            let next = cost + e.weight + h[vertex] - h[e.tail];
+
+            if next < reduced[e.tail] {
+                reduced[e.tail] = next;
+                parent[e.tail] = vertex;
+                heap.push(State{cost: next, vertex: e.tail});
+            }
+        }
+    }
+
+    // Undo the reweighting.
+    let dist = reduced.iter()
+                      .enumerate()
+                      .map(|(v, &d)| {
+                          if d == std::i32::MAX {
+                              d
+                          } else {
+                              d - h[source] + h[v]
+                          }
+                      })
+                      .collect();
+
+    (dist, parent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use Graph;
+
+    #[test]
+    fn bellman_ford_simple1() {
+        let g = Graph::from_file(Path::new("g_simple1.txt")).ok().unwrap();
+        let dist = bellman_ford(&g, 0).ok().unwrap();
+
+        assert_eq!(dist, vec![0, -2, -3, -1, -6, std::i32::MAX]);
+    }
+
+    #[test]
+    fn bellman_ford_negative_cycle() {
+        let g = Graph::from_file(Path::new("g_simple2.txt")).ok().unwrap();
+
+        assert!(bellman_ford(&g, 0).is_err());
+        assert!(potentials(&g).is_err());
+    }
+
+    #[test]
+    fn reweighted_matches_bellman_ford() {
+        let g = Graph::from_file(Path::new("g_simple1.txt")).ok().unwrap();
+        let h = potentials(&g).ok().unwrap();
+        let ex = Exclusions::none(g.vertices.len());
+
+        for v in &g.vertices {
+            for e in &v.edges {
+                assert!(e.weight + h[v.label as usize] - h[e.tail] >= 0);
+            }
+        }
+
+        let (path, cost) = path_between(&g, 0, 4, &h, &ex).unwrap();
+
+        assert_eq!(path, vec![0, 1, 2, 4]);
+        assert_eq!(cost, -6);
+        assert!(path_between(&g, 0, 5, &h, &ex).is_none());
+    }
+
+    #[test]
+    fn dijkstra_positive() {
+        let mut g = Graph::new();
+
+        g.build(4);
+        g.add_edge(0, 1, 4);
+        g.add_edge(0, 2, 1);
+        g.add_edge(2, 1, 2);
+        g.add_edge(1, 3, 5);
+
+        assert_eq!(dijkstra(&g, 0), vec![0, 3, 1, 8]);
+        assert_eq!(shortest_path(&g, 0, 3), Some((vec![0, 2, 1, 3], 8)));
+        assert_eq!(shortest_path(&g, 3, 0), None);
+    }
+}
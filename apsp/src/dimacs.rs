@@ -0,0 +1,293 @@
+// Readers and writers for the 9th DIMACS implementation challenge formats.
+//
+// Graphs (.gr):
+//   c <comment>
+//   p sp <vertices> <arcs>
+//   a <head> <tail> <weight>
+//
+// Coordinates (.co):
+//   c <comment>
+//   p aux sp co <vertices>
+//   v <vertex> <x> <y>
+//
+// Vertices are numbered from 1 in both files.
+
+use std::path::Path;
+use std::io::BufReader;
+use std::io::BufRead;
+use std::io::Write;
+use std::io::{Error, ErrorKind};
+use std::fs::File;
+
+use Graph;
+use multigraph::{Diagnostics, EdgePolicy};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinate {
+    pub x: i32,
+    pub y: i32,
+}
+
+fn invalid(line: usize, message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("line {}: {}", line, message))
+}
+
+fn fields<T: std::str::FromStr>(parts: &[&str], line: usize) -> Result<Vec<T>, Error> {
+    parts.iter()
+         .map(|p| p.parse::<T>().map_err(|_| invalid(line, &format!("invalid number {:?}", p))))
+         .collect()
+}
+
+fn vertex(n: i64, size: usize, line: usize) -> Result<usize, Error> {
+    if n < 1 || n as usize > size {
+        return Err(invalid(line, &format!("vertex {} out of range", n)));
+    }
+
+    Ok((n - 1) as usize)
+}
+
+pub fn read_graph<R: BufRead>(reader: R) -> Result<Graph, Error> {
+    let mut g = Graph::new();
+    let mut arcs: Option<usize> = None;
+    let mut seen = 0;
+
+    for (n, l) in reader.lines().enumerate() {
+        let line = try!(l);
+        let parts: Vec<&str> = line.split_whitespace().collect();
+
+        match parts.first() {
+            None | Some(&"c") => {},
+            Some(&"p") => {
+                if arcs.is_some() {
+                    return Err(invalid(n + 1, "duplicate problem line"));
+                }
+
+                if parts.len() != 4 || parts[1] != "sp" {
+                    return Err(invalid(n + 1, "expected \"p sp <vertices> <arcs>\""));
+                }
+
+                let sizes = try!(fields::<usize>(&parts[2..], n + 1));
+
+                g.build(sizes[0] as i32);
+                arcs = Some(sizes[1]);
+            },
+            Some(&"a") => {
+                if arcs.is_none() {
+                    return Err(invalid(n + 1, "arc before problem line"));
+                }
+
+                if parts.len() != 4 {
+                    return Err(invalid(n + 1, "expected \"a <head> <tail> <weight>\""));
+                }
+
+                let details = try!(fields::<i64>(&parts[1..], n + 1));
+                let head = try!(vertex(details[0], g.vertices.len(), n + 1));
+                let tail = try!(vertex(details[1], g.vertices.len(), n + 1));
+
+                if details[2] < std::i32::MIN as i64 || details[2] > std::i32::MAX as i64 {
+                    return Err(invalid(n + 1, "weight out of range"));
+                }
+
+                g.add_edge(head, tail, details[2] as i32);
+                seen += 1;
+            },
+            Some(other) => return Err(invalid(n + 1, &format!("unknown line type {:?}", other))),
+        }
+    }
+
+    match arcs {
+        None => Err(invalid(0, "missing problem line")),
+        Some(m) if m != seen => Err(invalid(0, &format!("expected {} arcs, found {}", m, seen))),
+        Some(_) => Ok(g),
+    }
+}
+
+pub fn read_coordinates<R: BufRead>(reader: R) -> Result<Vec<Coordinate>, Error> {
+    let mut coords: Option<Vec<Option<Coordinate>>> = None;
+
+    for (n, l) in reader.lines().enumerate() {
+        let line = try!(l);
+        let parts: Vec<&str> = line.split_whitespace().collect();
+
+        match parts.first() {
+            None | Some(&"c") => {},
+            Some(&"p") => {
+                if coords.is_some() {
+                    return Err(invalid(n + 1, "duplicate problem line"));
+                }
+
+                if parts.len() != 5 || parts[1..4] != ["aux", "sp", "co"] {
+                    return Err(invalid(n + 1, "expected \"p aux sp co <vertices>\""));
+                }
+
+                let size = try!(fields::<usize>(&parts[4..], n + 1))[0];
+
+                coords = Some(vec![None; size]);
+            },
+            Some(&"v") => {
+                let c = match coords {
+                    Some(ref mut c) => c,
+                    None => return Err(invalid(n + 1, "coordinate before problem line")),
+                };
+
+                if parts.len() != 4 {
+                    return Err(invalid(n + 1, "expected \"v <vertex> <x> <y>\""));
+                }
+
+                let details = try!(fields::<i64>(&parts[1..2], n + 1));
+                let point = try!(fields::<i32>(&parts[2..], n + 1));
+                let v = try!(vertex(details[0], c.len(), n + 1));
+
+                c[v] = Some(Coordinate{x: point[0], y: point[1]});
+            },
+            Some(other) => return Err(invalid(n + 1, &format!("unknown line type {:?}", other))),
+        }
+    }
+
+    match coords {
+        None => Err(invalid(0, "missing problem line")),
+        Some(c) => {
+            if let Some(v) = c.iter().position(|p| p.is_none()) {
+                return Err(invalid(0, &format!("missing coordinate for vertex {}", v + 1)));
+            }
+
+            Ok(c.into_iter().map(|p| p.unwrap()).collect())
+        },
+    }
+}
+
+pub fn write_graph<W: Write>(g: &Graph, out: &mut W) -> Result<(), Error> {
+    let arcs: usize = g.vertices.iter().map(|v| v.edges.len()).sum();
+
+    try!(writeln!(out, "p sp {} {}", g.vertices.len(), arcs));
+
+    for v in &g.vertices {
+        for e in &v.edges {
+            try!(writeln!(out, "a {} {} {}", v.label + 1, e.tail + 1, e.weight));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn write_coordinates<W: Write>(coords: &[Coordinate], out: &mut W) -> Result<(), Error> {
+    try!(writeln!(out, "p aux sp co {}", coords.len()));
+
+    for (i, c) in coords.iter().enumerate() {
+        try!(writeln!(out, "v {} {} {}", i + 1, c.x, c.y));
+    }
+
+    Ok(())
+}
+
+pub fn graph_from_file(path: &Path) -> Result<Graph, Error> {
+    let file = try!(File::open(path));
+
+    read_graph(BufReader::new(&file))
+}
+
+pub fn graph_from_file_with(path: &Path, policy: EdgePolicy) -> Result<(Graph, Diagnostics), Error> {
+    let mut g = try!(graph_from_file(path));
+
+    match g.apply_policy(policy) {
+        Ok(d) => Ok((g, d)),
+        Err(d) => Err(Error::new(ErrorKind::InvalidData, format!("{}", d))),
+    }
+}
+
+pub fn coordinates_from_file(path: &Path) -> Result<Vec<Coordinate>, Error> {
+    let file = try!(File::open(path));
+
+    read_coordinates(BufReader::new(&file))
+}
+
+pub fn graph_to_file(g: &Graph, path: &Path) -> Result<(), Error> {
+    let mut file = try!(File::create(path));
+
+    write_graph(g, &mut file)
+}
+
+pub fn coordinates_to_file(coords: &[Coordinate], path: &Path) -> Result<(), Error> {
+    let mut file = try!(File::create(path));
+
+    write_coordinates(coords, &mut file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use Graph;
+    use apsp;
+
+    #[test]
+    fn representation() {
+        let g = graph_from_file(Path::new("g_simple1.gr")).ok().unwrap();
+
+        assert_eq!(g.vertices.len(), 6);
+        assert_eq!(g.vertices[2].edges.len(), 3);
+
+        let ref e = g.vertices[5].edges[1];
+        assert_eq!(e.tail, 4);
+        assert_eq!(e.weight, -4);
+    }
+
+    #[test]
+    fn matches_course_format() {
+        let mut g = graph_from_file(Path::new("g_simple1.gr")).ok().unwrap();
+        let mut h = Graph::from_file(Path::new("g_simple1.txt")).ok().unwrap();
+
+        assert_eq!(apsp(&mut g), apsp(&mut h));
+    }
+
+    #[test]
+    fn round_trip() {
+        let g = Graph::from_file(Path::new("g_simple3.txt")).ok().unwrap();
+        let mut out: Vec<u8> = vec![];
+
+        write_graph(&g, &mut out).ok().unwrap();
+
+        let h = read_graph(&out[..]).ok().unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap().lines().next(), Some("p sp 6 5"));
+        assert_eq!(h.vertices.len(), g.vertices.len());
+
+        for (a, b) in g.vertices.iter().zip(h.vertices.iter()) {
+            let ea: Vec<(usize, i32)> = a.edges.iter().map(|e| (e.tail, e.weight)).collect();
+            let eb: Vec<(usize, i32)> = b.edges.iter().map(|e| (e.tail, e.weight)).collect();
+
+            assert_eq!(ea, eb);
+        }
+    }
+
+    #[test]
+    fn policy() {
+        let p = Path::new("g_simple1.gr");
+        let (_, d) = graph_from_file_with(p, EdgePolicy::strict()).ok().unwrap();
+
+        assert!(d.is_clean());
+    }
+
+    #[test]
+    fn coordinates() {
+        let coords = coordinates_from_file(Path::new("g_simple1.co")).ok().unwrap();
+
+        assert_eq!(coords.len(), 6);
+        assert_eq!(coords[0], Coordinate{x: -73530767, y: 41085396});
+
+        let mut out: Vec<u8> = vec![];
+
+        write_coordinates(&coords, &mut out).ok().unwrap();
+
+        assert_eq!(read_coordinates(&out[..]).ok().unwrap(), coords);
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(read_graph(&b"a 1 2 3\n"[..]).is_err());
+        assert!(read_graph(&b"p sp 2 1\na 1 3 5\n"[..]).is_err());
+        assert!(read_graph(&b"p sp 2 2\na 1 2 5\n"[..]).is_err());
+        assert!(read_graph(&b"p sp 2 1\nx 1 2 5\n"[..]).is_err());
+        assert!(read_coordinates(&b"p aux sp co 2\nv 1 0 0\n"[..]).is_err());
+    }
+}
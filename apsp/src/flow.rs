@@ -0,0 +1,544 @@
+use std::collections::VecDeque;
+
+use Graph;
+
+// Edge weights are read as capacities. Flow on each edge is reported in the same shape as
+// the graph: flow[v][i] is the flow along g.vertices[v].edges[i].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flow {
+    pub value: i64,
+    pub flow: Vec<Vec<i64>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cut {
+    pub capacity: i64,
+    pub source_side: Vec<bool>,
+    pub edges: Vec<(usize, usize)>,
+}
+
+// Residual network. Edge e and its reverse are stored at e and e ^ 1. Costs are only kept
+// for min-cost flow, where the reverse edge costs the negation of its forward edge.
+pub(crate) struct Network {
+    pub(crate) to: Vec<usize>,
+    pub(crate) cap: Vec<i64>,
+    pub(crate) cost: Vec<i64>,
+    pub(crate) adj: Vec<Vec<usize>>,
+}
+
+impl Network {
+    fn new(g: &Graph) -> Result<Network, &'static str> {
+        let capacity: Vec<Vec<i64>> = g.vertices.iter().map(|v| v.edges.iter().map(|e| e.weight as i64).collect()).collect();
+
+        Network::build(g, &capacity, false)
+    }
+
+    // Capacities given separately, with edge weights read as costs.
+    pub(crate) fn with_costs(g: &Graph, capacity: &[Vec<i64>]) -> Result<Network, &'static str> {
+        Network::build(g, capacity, true)
+    }
+
+    fn build(g: &Graph, capacity: &[Vec<i64>], costs: bool) -> Result<Network, &'static str> {
+        let len = g.vertices.len();
+        let mut n = Network{to: vec![], cap: vec![], cost: vec![], adj: vec![vec![]; len]};
+
+        for v in &g.vertices {
+            let head = v.label as usize;
+
+            for (i, e) in v.edges.iter().enumerate() {
+                if capacity[head][i] < 0 {
+                    return Err("Negative capacity");
+                }
+
+                let id = n.to.len();
+
+                n.to.push(e.tail);
+                n.cap.push(capacity[head][i]);
+                n.adj[head].push(id);
+                n.to.push(head);
+                n.cap.push(0);
+                n.adj[e.tail].push(id + 1);
+
+                if costs {
+                    n.cost.push(e.weight as i64);
+                    n.cost.push(-e.weight as i64);
+                }
+            }
+        }
+
+        Ok(n)
+    }
+
+    // Flow on each original edge is whatever has been pushed onto its reverse.
+    pub(crate) fn flows(&self, g: &Graph) -> Vec<Vec<i64>> {
+        let mut id = 0;
+        let mut flow = vec![];
+
+        for v in &g.vertices {
+            flow.push(v.edges.iter().map(|_| { id += 2; self.cap[id - 1] }).collect());
+        }
+
+        flow
+    }
+
+    // Vertices reachable from the source in the residual network.
+    fn reachable(&self, source: usize) -> Vec<bool> {
+        let mut seen = vec![false; self.adj.len()];
+        let mut queue = VecDeque::new();
+
+        seen[source] = true;
+        queue.push_back(source);
+
+        while let Some(v) = queue.pop_front() {
+            for &e in &self.adj[v] {
+                if self.cap[e] > 0 && !seen[self.to[e]] {
+                    seen[self.to[e]] = true;
+                    queue.push_back(self.to[e]);
+                }
+            }
+        }
+
+        seen
+    }
+
+    fn levels(&self, source: usize) -> Vec<Option<usize>> {
+        let mut level = vec![None; self.adj.len()];
+        let mut queue = VecDeque::new();
+
+        level[source] = Some(0);
+        queue.push_back(source);
+
+        while let Some(v) = queue.pop_front() {
+            for &e in &self.adj[v] {
+                if self.cap[e] > 0 && level[self.to[e]].is_none() {
+                    level[self.to[e]] = Some(level[v].unwrap() + 1);
+                    queue.push_back(self.to[e]);
+                }
+            }
+        }
+
+        level
+    }
+
+    fn dinic(&mut self, source: usize, sink: usize) -> i64 {
+        let mut value = 0;
+
+        loop {
+            let level = self.levels(source);
+
+            if level[sink].is_none() {
+                return value;
+            }
+
+            let mut next = vec![0; self.adj.len()];
+
+            loop {
+                let pushed = self.augment(source, sink, &level, &mut next);
+
+                if pushed == 0 {
+                    break;
+                }
+
+                value += pushed;
+            }
+        }
+    }
+
+    // One augmenting path along the level graph, found depth first with an explicit stack
+    // of edges so a long level graph cannot overflow the call stack. Edges that lead
+    // nowhere are skipped for the rest of the phase through next.
+    fn augment(&mut self, source: usize, sink: usize, level: &[Option<usize>], next: &mut [usize]) -> i64 {
+        let mut path: Vec<usize> = vec![];
+
+        loop {
+            let v = path.last().map_or(source, |&e| self.to[e]);
+
+            if v == sink {
+                let pushed = path.iter().map(|&e| self.cap[e]).min().unwrap();
+
+                for &e in &path {
+                    self.cap[e] -= pushed;
+                    self.cap[e ^ 1] += pushed;
+                }
+
+                return pushed;
+            }
+
+            if next[v] < self.adj[v].len() {
+                let e = self.adj[v][next[v]];
+
+                if self.cap[e] > 0 && level[self.to[e]] == level[v].map(|l| l + 1) {
+                    path.push(e);
+                } else {
+                    next[v] += 1;
+                }
+
+                continue;
+            }
+
+            match path.pop() {
+                Some(e) => next[self.to[e ^ 1]] += 1,
+                None => return 0,
+            }
+        }
+    }
+}
+
+fn check(g: &Graph, source: usize, sink: usize) -> Result<(), &'static str> {
+    if source >= g.vertices.len() || sink >= g.vertices.len() {
+        return Err("Vertex out of range");
+    }
+
+    if source == sink {
+        return Err("Source and sink are the same");
+    }
+
+    Ok(())
+}
+
+// Dinic's algorithm: blocking flows along BFS level graphs, O(n²m).
+pub fn dinic(g: &Graph, source: usize, sink: usize) -> Result<Flow, &'static str> {
+    try!(check(g, source, sink));
+
+    let mut n = try!(Network::new(g));
+    let value = n.dinic(source, sink);
+
+    Ok(Flow{value: value, flow: n.flows(g)})
+}
+
+// FIFO push-relabel, O(n³). Runs until no vertex holds excess, so the preflow ends up a
+// valid flow.
+pub fn push_relabel(g: &Graph, source: usize, sink: usize) -> Result<Flow, &'static str> {
+    try!(check(g, source, sink));
+
+    let len = g.vertices.len();
+    let mut n = try!(Network::new(g));
+    let mut height = vec![0; len];
+    let mut excess = vec![0i64; len];
+    let mut active = VecDeque::new();
+    let mut queued = vec![false; len];
+
+    height[source] = len;
+
+    for i in 0..n.adj[source].len() {
+        let e = n.adj[source][i];
+        let (w, c) = (n.to[e], n.cap[e]);
+
+        n.cap[e] = 0;
+        n.cap[e ^ 1] += c;
+        excess[w] += c;
+        excess[source] -= c;
+
+        if c > 0 && w != sink && !queued[w] {
+            queued[w] = true;
+            active.push_back(w);
+        }
+    }
+
+    while let Some(v) = active.pop_front() {
+        queued[v] = false;
+
+        while excess[v] > 0 {
+            let mut lowest = std::usize::MAX;
+
+            for i in 0..n.adj[v].len() {
+                let e = n.adj[v][i];
+                let w = n.to[e];
+
+                if n.cap[e] == 0 {
+                    continue;
+                }
+
+                if height[v] == height[w] + 1 {
+                    let pushed = std::cmp::min(excess[v], n.cap[e]);
+
+                    n.cap[e] -= pushed;
+                    n.cap[e ^ 1] += pushed;
+                    excess[v] -= pushed;
+                    excess[w] += pushed;
+
+                    if w != source && w != sink && !queued[w] {
+                        queued[w] = true;
+                        active.push_back(w);
+                    }
+
+                    if excess[v] == 0 {
+                        break;
+                    }
+                } else {
+                    lowest = std::cmp::min(lowest, height[w]);
+                }
+            }
+
+            if excess[v] > 0 {
+                height[v] = lowest + 1;
+            }
+        }
+    }
+
+    Ok(Flow{value: excess[sink], flow: n.flows(g)})
+}
+
+// The minimum s-t cut, read off the residual network of a maximum flow.
+pub fn min_cut(g: &Graph, source: usize, sink: usize) -> Result<Cut, &'static str> {
+    try!(check(g, source, sink));
+
+    let mut n = try!(Network::new(g));
+    let value = n.dinic(source, sink);
+
+    let side = n.reachable(source);
+    let mut edges = vec![];
+
+    for v in &g.vertices {
+        for e in &v.edges {
+            if side[v.label as usize] && !side[e.tail] {
+                edges.push((v.label as usize, e.tail));
+            }
+        }
+    }
+
+    Ok(Cut{capacity: value, source_side: side, edges: edges})
+}
+
+// Stoer-Wagner global minimum cut, O(n³). Each edge is read as an undirected edge of that
+// weight, so a file listing both directions counts each pair twice.
+pub fn global_min_cut(g: &Graph) -> Result<Cut, &'static str> {
+    let len = g.vertices.len();
+
+    if len < 2 {
+        return Err("Need at least two vertices");
+    }
+
+    let mut w = vec![vec![0i64; len]; len];
+
+    for v in &g.vertices {
+        for e in &v.edges {
+            if e.weight < 0 {
+                return Err("Negative capacity");
+            }
+
+            let head = v.label as usize;
+
+            if head != e.tail {
+                w[head][e.tail] += e.weight as i64;
+                w[e.tail][head] += e.weight as i64;
+            }
+        }
+    }
+
+    // Each super vertex remembers the original vertices merged into it.
+    let mut merged: Vec<Vec<usize>> = (0..len).map(|v| vec![v]).collect();
+    let mut alive: Vec<usize> = (0..len).collect();
+    let mut best: Option<(i64, Vec<usize>)> = None;
+
+    while alive.len() > 1 {
+        // Maximum adjacency order.
+        let mut weight: Vec<i64> = vec![0; len];
+        let mut added = vec![false; len];
+        let mut previous = alive[0];
+        let mut last = alive[0];
+
+        for _ in 0..alive.len() {
+            let mut pick = None;
+
+            for &v in &alive {
+                if !added[v] && pick.map_or(true, |p| weight[v] > weight[p]) {
+                    pick = Some(v);
+                }
+            }
+
+            let v = pick.unwrap();
+
+            added[v] = true;
+            previous = last;
+            last = v;
+
+            for &u in &alive {
+                weight[u] += w[v][u];
+            }
+        }
+
+        // The cut of the phase separates the last vertex from everything else.
+        if best.as_ref().map_or(true, |&(b, _)| weight[last] < b) {
+            best = Some((weight[last], merged[last].clone()));
+        }
+
+        let moved = merged[last].clone();
+
+        merged[previous].extend(moved);
+
+        for &u in &alive {
+            w[previous][u] += w[last][u];
+            w[u][previous] = w[previous][u];
+        }
+
+        w[previous][previous] = 0;
+        alive.retain(|&v| v != last);
+    }
+
+    let (capacity, side) = best.unwrap();
+    let mut source_side = vec![false; len];
+    let mut edges = vec![];
+
+    for v in side {
+        source_side[v] = true;
+    }
+
+    for v in &g.vertices {
+        for e in &v.edges {
+            if source_side[v.label as usize] != source_side[e.tail] {
+                edges.push((v.label as usize, e.tail));
+            }
+        }
+    }
+
+    Ok(Cut{capacity: capacity, source_side: source_side, edges: edges})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use Graph;
+    use rng::Rng;
+
+    fn random_graph<F: FnMut() -> i32>(next: &mut F) -> Graph {
+        let mut g = Graph::new();
+        let size = 2 + (next() % 6) as usize;
+
+        g.build(size as i32);
+
+        for h in 0..size {
+            for t in 0..size {
+                if h != t && next() % 3 == 0 {
+                    g.add_edge(h, t, next() % 10);
+                }
+            }
+        }
+
+        g
+    }
+
+    // Capacity of the cut around a set of vertices, directed or not.
+    fn cut_capacity(g: &Graph, side: &[bool], directed: bool) -> i64 {
+        let mut c = 0;
+
+        for v in &g.vertices {
+            for e in &v.edges {
+                let (a, b) = (side[v.label as usize], side[e.tail]);
+
+                if (a && !b) || (!directed && !a && b) {
+                    c += e.weight as i64;
+                }
+            }
+        }
+
+        c
+    }
+
+    fn conserves(g: &Graph, f: &Flow, source: usize, sink: usize) -> bool {
+        let mut net = vec![0; g.vertices.len()];
+
+        for v in &g.vertices {
+            for (i, e) in v.edges.iter().enumerate() {
+                let x = f.flow[v.label as usize][i];
+
+                if x < 0 || x > e.weight as i64 {
+                    return false;
+                }
+
+                net[v.label as usize] -= x;
+                net[e.tail] += x;
+            }
+        }
+
+        (0..net.len()).all(|v| v == source || v == sink || net[v] == 0) && net[sink] == f.value
+    }
+
+    #[test]
+    fn clrs() {
+        let g = Graph::from_file(Path::new("flow_simple1.txt")).ok().unwrap();
+        let d = dinic(&g, 0, 5).ok().unwrap();
+        let p = push_relabel(&g, 0, 5).ok().unwrap();
+        let c = min_cut(&g, 0, 5).ok().unwrap();
+
+        assert_eq!(d.value, 23);
+        assert_eq!(p.value, 23);
+        assert!(conserves(&g, &d, 0, 5));
+        assert!(conserves(&g, &p, 0, 5));
+
+        assert_eq!(c.capacity, 23);
+        assert_eq!(c.source_side, vec![true, true, true, false, true, false]);
+        assert_eq!(c.edges, vec![(1, 3), (4, 3), (4, 5)]);
+    }
+
+    #[test]
+    fn invalid() {
+        let g = Graph::from_file(Path::new("g_simple1.txt")).ok().unwrap();
+
+        assert!(dinic(&g, 0, 4).is_err());
+        assert!(push_relabel(&g, 0, 4).is_err());
+
+        let g = Graph::from_file(Path::new("flow_simple1.txt")).ok().unwrap();
+
+        assert!(dinic(&g, 2, 2).is_err());
+    }
+
+    #[test]
+    fn brute_force() {
+        let mut rng = Rng::new(5);
+        let mut next = || rng.next() as i32;
+
+        for _ in 0..100 {
+            let g = random_graph(&mut next);
+            let len = g.vertices.len();
+            let sides: Vec<Vec<bool>> = (0..(1 << len)).map(|bits: usize| (0..len).map(|i| bits & (1 << i) != 0).collect()).collect();
+
+            let s_t = sides.iter()
+                           .filter(|s| s[0] && !s[len - 1])
+                           .map(|s| cut_capacity(&g, s, true))
+                           .min()
+                           .unwrap();
+
+            let d = dinic(&g, 0, len - 1).ok().unwrap();
+            let p = push_relabel(&g, 0, len - 1).ok().unwrap();
+            let c = min_cut(&g, 0, len - 1).ok().unwrap();
+
+            assert_eq!(d.value, s_t);
+            assert_eq!(p.value, s_t);
+            assert!(conserves(&g, &d, 0, len - 1));
+            assert!(conserves(&g, &p, 0, len - 1));
+            assert_eq!(c.capacity, s_t);
+            assert_eq!(cut_capacity(&g, &c.source_side, true), s_t);
+
+            let global = sides.iter()
+                              .filter(|s| s.iter().any(|&b| b) && !s.iter().all(|&b| b))
+                              .map(|s| cut_capacity(&g, s, false))
+                              .min()
+                              .unwrap();
+
+            let sw = global_min_cut(&g).ok().unwrap();
+
+            assert_eq!(sw.capacity, global);
+            assert_eq!(cut_capacity(&g, &sw.source_side, false), global);
+        }
+    }
+
+    #[test]
+    fn long_chain() {
+        let mut g = Graph::new();
+        let size = 100000;
+
+        g.build(size as i32);
+
+        for v in 0..(size - 1) {
+            g.add_edge(v, v + 1, 5 + (v % 7) as i32);
+        }
+
+        let d = dinic(&g, 0, size - 1).ok().unwrap();
+
+        assert_eq!(d.value, 5);
+        assert!(conserves(&g, &d, 0, size - 1));
+        assert_eq!(min_cut(&g, 0, size - 1).ok().unwrap().edges, vec![(0, 1)]);
+    }
+}
@@ -3,6 +3,20 @@ use std::io::BufReader;
 use std::io::BufRead;
 use std::fs::File;
 
+pub mod astar;
+pub mod ch;
+pub mod closure;
+pub mod cycles;
+pub mod dijkstra;
+pub mod dimacs;
+pub mod flow;
+pub mod mincost;
+pub mod multigraph;
+pub mod oracle;
+pub mod paths;
+pub mod scc;
+pub mod two_sat;
+
 #[derive(Debug)]
 pub struct Graph {
     vertices: Vec<Vertex>,
@@ -32,6 +46,25 @@ impl Graph {
         }
     }
 
+    fn add_edge(&mut self, head: usize, tail: usize, weight: i32) {
+        self.vertices[head].edges.push(Edge{tail: tail, weight: weight});
+    }
+
+    // The same vertices with every edge turned around.
+    pub fn reversed(&self) -> Graph {
+        let mut r = Graph::new();
+
+        r.build(self.vertices.len() as i32);
+
+        for v in &self.vertices {
+            for e in &v.edges {
+                r.add_edge(e.tail, v.label as usize, e.weight);
+            }
+        }
+
+        r
+    }
+
     fn split_line<T: std::str::FromStr>(line: &str) -> Vec<T> {
         line.split(" ")
             .map(|d| { 
@@ -44,7 +77,7 @@ impl Graph {
             .collect()
     }
 
-    fn from_file(path: &Path) -> Result<Graph, std::io::Error> {
+    pub fn from_file(path: &Path) -> Result<Graph, std::io::Error> {
         let mut g = Graph{vertices: vec![]};
         let file = try!(File::open(path));
         let mut buffer = BufReader::new(&file);
@@ -86,7 +119,11 @@ pub fn apsp(g: &mut Graph) -> Result<i32, &'static str> {
 
     for v in &g.vertices {
         for e in &v.edges {
-            dist[v.label as usize][e.tail] = e.weight;
+            let ref mut d = dist[v.label as usize][e.tail];
+
+            // Take the cheapest of any parallel edges. A negative self-loop stays on the
+            // diagonal and is reported as a negative cycle.
+            *d = std::cmp::min(*d, e.weight);
         }
     }
 
@@ -113,6 +150,26 @@ pub fn apsp(g: &mut Graph) -> Result<i32, &'static str> {
     Ok(smallest)
 }
 
+// The linear congruential generator the randomised tests draw from.
+#[cfg(test)]
+mod rng {
+    pub struct Rng {
+        seed: u32,
+    }
+
+    impl Rng {
+        pub fn new(seed: u32) -> Rng {
+            Rng{seed: seed}
+        }
+
+        // 15 bits at a time.
+        pub fn next(&mut self) -> u32 {
+            self.seed = self.seed.wrapping_mul(1103515245).wrapping_add(12345);
+            self.seed >> 16
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
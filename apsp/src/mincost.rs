@@ -0,0 +1,408 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use Graph;
+use flow::Network;
+
+// Edge weights are read as costs per unit of flow, with capacities given separately in
+// the same shape: capacity[v][i] belongs to g.vertices[v].edges[i], as does flow[v][i].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostFlow {
+    pub value: i64,
+    pub cost: i64,
+    pub flow: Vec<Vec<i64>>,
+}
+
+// Column assigned to each row, and the total cost. With more rows than columns some rows
+// go unassigned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub columns: Vec<Option<usize>>,
+    pub cost: i64,
+}
+
+// Every edge with capacity 1.
+pub fn unit_capacities(g: &Graph) -> Vec<Vec<i64>> {
+    g.vertices.iter().map(|v| vec![1; v.edges.len()]).collect()
+}
+
+impl Network {
+    // Johnson potentials over the residual edges with capacity left, by Bellman-Ford from a
+    // virtual source.
+    fn potentials(&self) -> Result<Vec<i64>, &'static str> {
+        let len = self.adj.len();
+        let mut h = vec![0; len];
+
+        for i in 0..(len + 1) {
+            let mut changed = false;
+
+            for v in 0..len {
+                for &e in &self.adj[v] {
+                    if self.cap[e] > 0 && h[v] + self.cost[e] < h[self.to[e]] {
+                        if i == len {
+                            return Err("Negative cycle");
+                        }
+
+                        h[self.to[e]] = h[v] + self.cost[e];
+                        changed = true;
+                    }
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        Ok(h)
+    }
+
+    // Dijkstra on reduced costs. Returns distances and the edge used to reach each vertex.
+    fn shortest(&self, source: usize, h: &[i64]) -> (Vec<i64>, Vec<Option<usize>>) {
+        let len = self.adj.len();
+        let mut dist = vec![std::i64::MAX; len];
+        let mut via = vec![None; len];
+        let mut heap = BinaryHeap::new();
+
+        dist[source] = 0;
+        heap.push(Reverse((0, source)));
+
+        while let Some(Reverse((d, v))) = heap.pop() {
+            if d > dist[v] {
+                continue;
+            }
+
+            for &e in &self.adj[v] {
+                let w = self.to[e];
+
+                if self.cap[e] == 0 {
+                    continue;
+                }
+
+                let next = d + self.cost[e] + h[v] - h[w];
+
+                if next < dist[w] {
+                    dist[w] = next;
+                    via[w] = Some(e);
+                    heap.push(Reverse((next, w)));
+                }
+            }
+        }
+
+        (dist, via)
+    }
+}
+
+// Successive shortest paths: repeatedly augment along the cheapest residual path, keeping
+// reduced costs non-negative with Johnson-style potentials. Sends at most `limit` units
+// (std::i64::MAX for a maximum flow) at minimum cost. Negative costs are fine as long as
+// there is no negative cycle.
+pub fn min_cost_flow(g: &Graph, capacity: &[Vec<i64>], source: usize, sink: usize, limit: i64) -> Result<CostFlow, &'static str> {
+    let len = g.vertices.len();
+
+    if source >= len || sink >= len {
+        return Err("Vertex out of range");
+    }
+
+    if source == sink {
+        return Err("Source and sink are the same");
+    }
+
+    let mut n = try!(Network::with_costs(g, capacity));
+
+    let mut h = try!(n.potentials());
+    let (mut value, mut cost) = (0, 0);
+
+    while value < limit {
+        let (dist, via) = n.shortest(source, &h);
+
+        if dist[sink] == std::i64::MAX {
+            break;
+        }
+
+        for v in 0..len {
+            if dist[v] != std::i64::MAX {
+                h[v] += dist[v];
+            }
+        }
+
+        let mut push = limit - value;
+        let mut v = sink;
+
+        while let Some(e) = via[v] {
+            push = std::cmp::min(push, n.cap[e]);
+            v = n.to[e ^ 1];
+        }
+
+        v = sink;
+
+        while let Some(e) = via[v] {
+            n.cap[e] -= push;
+            n.cap[e ^ 1] += push;
+            cost += push * n.cost[e];
+            v = n.to[e ^ 1];
+        }
+
+        value += push;
+    }
+
+    Ok(CostFlow{value: value, cost: cost, flow: n.flows(g)})
+}
+
+// The Hungarian algorithm with potentials, O(n²m) for n rows and m columns. Finds the
+// cheapest way to give each row its own column, or each column its own row when there are
+// more rows than columns. Every row must have the same number of columns.
+pub fn hungarian(costs: &[Vec<i64>]) -> Assignment {
+    let rows = costs.len();
+    let cols = if rows == 0 { 0 } else { costs[0].len() };
+
+    if let Some(r) = costs.iter().position(|row| row.len() != cols) {
+        panic!("Invalid costs: row {:?} has {:?} columns, not {:?}", r, costs[r].len(), cols);
+    }
+
+    if rows > cols {
+        let transposed: Vec<Vec<i64>> = (0..cols).map(|c| (0..rows).map(|r| costs[r][c]).collect()).collect();
+        let t = hungarian(&transposed);
+        let mut columns = vec![None; rows];
+
+        for (c, r) in t.columns.iter().enumerate() {
+            if let Some(r) = *r {
+                columns[r] = Some(c);
+            }
+        }
+
+        return Assignment{columns: columns, cost: t.cost};
+    }
+
+    // 1-based, with row and column 0 as sentinels.
+    let inf = std::i64::MAX;
+    let mut u = vec![0i64; rows + 1];
+    let mut v = vec![0i64; cols + 1];
+    let mut owner = vec![0usize; cols + 1];
+    let mut way = vec![0usize; cols + 1];
+
+    for r in 1..(rows + 1) {
+        let mut minv = vec![inf; cols + 1];
+        let mut used = vec![false; cols + 1];
+        let mut c0 = 0;
+
+        owner[0] = r;
+
+        loop {
+            used[c0] = true;
+
+            let r0 = owner[c0];
+            let mut delta = inf;
+            let mut c1 = 0;
+
+            for c in 1..(cols + 1) {
+                if used[c] {
+                    continue;
+                }
+
+                let reduced = costs[r0 - 1][c - 1] - u[r0] - v[c];
+
+                if reduced < minv[c] {
+                    minv[c] = reduced;
+                    way[c] = c0;
+                }
+
+                if minv[c] < delta {
+                    delta = minv[c];
+                    c1 = c;
+                }
+            }
+
+            for c in 0..(cols + 1) {
+                if used[c] {
+                    u[owner[c]] += delta;
+                    v[c] -= delta;
+                } else {
+                    minv[c] -= delta;
+                }
+            }
+
+            c0 = c1;
+
+            if owner[c0] == 0 {
+                break;
+            }
+        }
+
+        // Flip the alternating path back to the root.
+        loop {
+            let c1 = way[c0];
+
+            owner[c0] = owner[c1];
+            c0 = c1;
+
+            if c0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut columns = vec![None; rows];
+    let mut cost = 0;
+
+    for c in 1..(cols + 1) {
+        if owner[c] != 0 {
+            columns[owner[c] - 1] = Some(c - 1);
+            cost += costs[owner[c] - 1][c - 1];
+        }
+    }
+
+    Assignment{columns: columns, cost: cost}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use Graph;
+    use rng::Rng;
+
+    fn random<F: FnMut() -> i32>(next: &mut F, rows: usize, cols: usize) -> Vec<Vec<i64>> {
+        (0..rows).map(|_| (0..cols).map(|_| (next() % 50 - 10) as i64).collect()).collect()
+    }
+
+    // Cheapest assignment over every permutation of columns.
+    fn brute_force(costs: &[Vec<i64>]) -> i64 {
+        fn extend(costs: &[Vec<i64>], row: usize, used: &mut Vec<bool>) -> i64 {
+            if row == costs.len() {
+                return 0;
+            }
+
+            let mut best = std::i64::MAX;
+
+            for c in 0..used.len() {
+                if !used[c] {
+                    used[c] = true;
+                    best = std::cmp::min(best, costs[row][c] + extend(costs, row + 1, used));
+                    used[c] = false;
+                }
+            }
+
+            best
+        }
+
+        extend(costs, 0, &mut vec![false; costs[0].len()])
+    }
+
+    #[test]
+    fn hungarian_simple() {
+        let costs = vec![vec![4, 1, 3], vec![2, 0, 5], vec![3, 2, 2]];
+        let a = hungarian(&costs);
+
+        assert_eq!(a.cost, 5);
+        assert_eq!(a.columns, vec![Some(1), Some(0), Some(2)]);
+
+        let wide = hungarian(&[vec![7, 3, 9]]);
+
+        assert_eq!(wide, Assignment{columns: vec![Some(1)], cost: 3});
+
+        let tall = hungarian(&[vec![7], vec![3], vec![9]]);
+
+        assert_eq!(tall, Assignment{columns: vec![None, Some(0), None], cost: 3});
+    }
+
+    #[test]
+    #[should_panic]
+    fn hungarian_ragged() {
+        hungarian(&[vec![1, 2], vec![3]]);
+    }
+
+    #[test]
+    fn hungarian_brute_force() {
+        let mut rng = Rng::new(9);
+        let mut next = || rng.next() as i32;
+
+        for _ in 0..100 {
+            let rows = 1 + (next() % 5) as usize;
+            let cols = rows + (next() % 3) as usize;
+            let costs = random(&mut next, rows, cols);
+            let a = hungarian(&costs);
+            let mut seen = vec![false; cols];
+
+            for c in &a.columns {
+                let c = c.unwrap();
+
+                assert!(!seen[c]);
+                seen[c] = true;
+            }
+
+            assert_eq!(a.cost, brute_force(&costs));
+            assert_eq!(a.cost, a.columns.iter().enumerate().map(|(r, c)| costs[r][c.unwrap()]).sum::<i64>());
+        }
+    }
+
+    #[test]
+    fn flow_matches_hungarian() {
+        let mut rng = Rng::new(21);
+        let mut next = || rng.next() as i32;
+
+        for _ in 0..50 {
+            let size = 1 + (next() % 5) as usize;
+            let costs = random(&mut next, size, size);
+            let mut g = Graph::new();
+
+            // Source, rows, columns, sink.
+            g.build(2 * size as i32 + 2);
+
+            for r in 0..size {
+                g.add_edge(0, 1 + r, 0);
+
+                for c in 0..size {
+                    g.add_edge(1 + r, 1 + size + c, costs[r][c] as i32);
+                }
+            }
+
+            for c in 0..size {
+                g.add_edge(1 + size + c, 2 * size + 1, 0);
+            }
+
+            let f = min_cost_flow(&g, &unit_capacities(&g), 0, 2 * size + 1, std::i64::MAX).ok().unwrap();
+
+            assert_eq!(f.value, size as i64);
+            assert_eq!(f.cost, hungarian(&costs).cost);
+        }
+    }
+
+    #[test]
+    fn limited_flow() {
+        let g = Graph::from_file(Path::new("flow_simple1.txt")).ok().unwrap();
+        let capacity: Vec<Vec<i64>> = g.vertices.iter().map(|v| v.edges.iter().map(|e| e.weight as i64).collect()).collect();
+
+        // Unit cost per edge, so the cost is the total flow-weighted path length.
+        let mut unit = Graph::new();
+
+        unit.build(g.vertices.len() as i32);
+
+        for v in &g.vertices {
+            for e in &v.edges {
+                unit.add_edge(v.label as usize, e.tail, 1);
+            }
+        }
+
+        let all = min_cost_flow(&unit, &capacity, 0, 5, std::i64::MAX).ok().unwrap();
+        let some = min_cost_flow(&unit, &capacity, 0, 5, 10).ok().unwrap();
+
+        assert_eq!(all.value, 23);
+        assert_eq!(some.value, 10);
+
+        // 10 units fit along three edge paths.
+        assert_eq!(some.cost, 30);
+        assert_eq!(some.flow[0].iter().sum::<i64>(), 10);
+
+        let total: i64 = all.flow.iter().map(|f| f.iter().sum::<i64>()).sum();
+
+        assert_eq!(all.cost, total);
+    }
+
+    #[test]
+    fn negative_cycle() {
+        let g = Graph::from_file(Path::new("g_simple2.txt")).ok().unwrap();
+
+        assert!(min_cost_flow(&g, &unit_capacities(&g), 0, 3, 1).is_err());
+    }
+}
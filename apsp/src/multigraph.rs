@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::io::{Error, ErrorKind};
+
+use Graph;
+use Edge;
+
+// What to do with several edges between the same ordered pair of vertices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Parallel {
+    KeepMin,
+    KeepMax,
+    KeepAll,
+    Reject,
+}
+
+// What to do with edges from a vertex to itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelfLoops {
+    Keep,
+    Drop,
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdgePolicy {
+    pub parallel: Parallel,
+    pub self_loops: SelfLoops,
+}
+
+impl EdgePolicy {
+    // Keeps every edge as read, as Graph::from_file does.
+    pub fn permissive() -> EdgePolicy {
+        EdgePolicy{parallel: Parallel::KeepAll, self_loops: SelfLoops::Keep}
+    }
+
+    // Keeps the cheapest of any parallel edges, which is all a shortest path can use.
+    pub fn shortest_paths() -> EdgePolicy {
+        EdgePolicy{parallel: Parallel::KeepMin, self_loops: SelfLoops::Keep}
+    }
+
+    pub fn strict() -> EdgePolicy {
+        EdgePolicy{parallel: Parallel::Reject, self_loops: SelfLoops::Reject}
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    pub head: usize,
+    pub tail: usize,
+    pub weights: Vec<i32>,
+}
+
+// Parallel edges and self-loops found while applying a policy, whatever was done with them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostics {
+    pub parallel: Vec<Duplicate>,
+    pub self_loops: Vec<(usize, i32)>,
+}
+
+impl Diagnostics {
+    pub fn is_clean(&self) -> bool {
+        self.parallel.is_empty() && self.self_loops.is_empty()
+    }
+}
+
+// Vertices are printed from 1, as in the input files.
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{} parallel edge group(s), {} self-loop(s)",
+                    self.parallel.len(), self.self_loops.len()));
+
+        for d in &self.parallel {
+            try!(write!(f, "\n  parallel {} -> {}: {:?}", d.head + 1, d.tail + 1, d.weights));
+        }
+
+        for &(v, w) in &self.self_loops {
+            try!(write!(f, "\n  self-loop {}: {}", v + 1, w));
+        }
+
+        Ok(())
+    }
+}
+
+impl Graph {
+    // Resolves parallel edges and self-loops according to the policy. If the policy rejects
+    // something that is present, the graph is left untouched and the offending edges are
+    // returned as the error.
+    pub fn apply_policy(&mut self, policy: EdgePolicy) -> Result<Diagnostics, Diagnostics> {
+        let mut diagnostics = Diagnostics{parallel: vec![], self_loops: vec![]};
+
+        for v in &self.vertices {
+            let head = v.label as usize;
+            let mut groups: HashMap<usize, Vec<i32>> = HashMap::new();
+
+            for e in &v.edges {
+                if e.tail == head {
+                    diagnostics.self_loops.push((head, e.weight));
+                }
+
+                groups.entry(e.tail).or_insert_with(Vec::new).push(e.weight);
+            }
+
+            let mut tails: Vec<usize> = groups.iter()
+                                              .filter(|&(_, ws)| ws.len() > 1)
+                                              .map(|(&t, _)| t)
+                                              .collect();
+
+            tails.sort();
+
+            for t in tails {
+                diagnostics.parallel.push(Duplicate{head: head, tail: t, weights: groups[&t].clone()});
+            }
+        }
+
+        let rejected = (policy.parallel == Parallel::Reject && !diagnostics.parallel.is_empty()) ||
+                       (policy.self_loops == SelfLoops::Reject && !diagnostics.self_loops.is_empty());
+
+        if rejected {
+            return Err(diagnostics);
+        }
+
+        for v in &mut self.vertices {
+            let head = v.label as usize;
+            let mut kept: Vec<Edge> = vec![];
+            let mut position: HashMap<usize, usize> = HashMap::new();
+
+            for e in v.edges.drain(..) {
+                if e.tail == head && policy.self_loops == SelfLoops::Drop {
+                    continue;
+                }
+
+                if policy.parallel == Parallel::KeepAll {
+                    kept.push(e);
+                    continue;
+                }
+
+                match position.get(&e.tail) {
+                    Some(&i) => {
+                        let better = match policy.parallel {
+                            Parallel::KeepMin => e.weight < kept[i].weight,
+                            _ => e.weight > kept[i].weight,
+                        };
+
+                        if better {
+                            kept[i].weight = e.weight;
+                        }
+
+                        continue;
+                    },
+                    None => {},
+                }
+
+                position.insert(e.tail, kept.len());
+                kept.push(e);
+            }
+
+            v.edges = kept;
+        }
+
+        Ok(diagnostics)
+    }
+
+    pub fn from_file_with(path: &Path, policy: EdgePolicy) -> Result<(Graph, Diagnostics), Error> {
+        let mut g = try!(Graph::from_file(path));
+
+        match g.apply_policy(policy) {
+            Ok(d) => Ok((g, d)),
+            Err(d) => Err(Error::new(ErrorKind::InvalidData, format!("{}", d))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Graph;
+    use apsp;
+
+    fn multigraph() -> Graph {
+        let mut g = Graph::new();
+
+        g.build(3);
+        g.add_edge(0, 1, 5);
+        g.add_edge(0, 2, 9);
+        g.add_edge(0, 1, 2);
+        g.add_edge(0, 1, 7);
+        g.add_edge(1, 1, 3);
+        g.add_edge(1, 2, 1);
+
+        g
+    }
+
+    fn edges(g: &Graph, v: usize) -> Vec<(usize, i32)> {
+        g.vertices[v].edges.iter().map(|e| (e.tail, e.weight)).collect()
+    }
+
+    #[test]
+    fn diagnostics() {
+        let mut g = multigraph();
+        let d = g.apply_policy(EdgePolicy::permissive()).ok().unwrap();
+
+        assert_eq!(d.parallel, vec![Duplicate{head: 0, tail: 1, weights: vec![5, 2, 7]}]);
+        assert_eq!(d.self_loops, vec![(1, 3)]);
+        assert!(!d.is_clean());
+        assert_eq!(edges(&g, 0).len(), 4);
+        assert_eq!(format!("{}", d).lines().nth(1), Some("  parallel 1 -> 2: [5, 2, 7]"));
+    }
+
+    #[test]
+    fn keep_min_and_max() {
+        let mut g = multigraph();
+
+        g.apply_policy(EdgePolicy{parallel: Parallel::KeepMin, self_loops: SelfLoops::Drop}).ok().unwrap();
+
+        assert_eq!(edges(&g, 0), vec![(1, 2), (2, 9)]);
+        assert_eq!(edges(&g, 1), vec![(2, 1)]);
+
+        let mut g = multigraph();
+
+        g.apply_policy(EdgePolicy{parallel: Parallel::KeepMax, self_loops: SelfLoops::Keep}).ok().unwrap();
+
+        assert_eq!(edges(&g, 0), vec![(1, 7), (2, 9)]);
+        assert_eq!(edges(&g, 1), vec![(1, 3), (2, 1)]);
+    }
+
+    #[test]
+    fn reject() {
+        let mut g = multigraph();
+
+        assert!(g.apply_policy(EdgePolicy::strict()).is_err());
+        assert_eq!(edges(&g, 0).len(), 4);
+
+        let mut g = multigraph();
+        let policy = EdgePolicy{parallel: Parallel::KeepMin, self_loops: SelfLoops::Reject};
+
+        assert_eq!(g.apply_policy(policy).err().unwrap().self_loops, vec![(1, 3)]);
+    }
+
+    #[test]
+    fn apsp_uses_cheapest_parallel_edge() {
+        let mut g = Graph::new();
+
+        g.build(2);
+        g.add_edge(0, 1, -1);
+        g.add_edge(0, 1, 4);
+
+        assert_eq!(apsp(&mut g), Ok(-1));
+
+        g.add_edge(1, 1, -2);
+
+        assert!(apsp(&mut g).is_err());
+    }
+
+    #[test]
+    fn from_file() {
+        let p = Path::new("g_simple1.txt");
+        let (g, d) = Graph::from_file_with(p, EdgePolicy::strict()).ok().unwrap();
+
+        assert!(d.is_clean());
+        assert_eq!(g.vertices.len(), 6);
+    }
+}
@@ -0,0 +1,521 @@
+// A precomputed all pairs distance oracle and its on-disk format. All values are little
+// endian.
+//
+//   magic        8 bytes  "APSPORCL"
+//   version      u16      1
+//   weight type  u8       1 = i32
+//   flags        u8       bit 0 set if next hops follow the distances
+//   vertices     u64      n
+//   checksum     u64      FNV-1a over everything after the header
+//   distances    n * n i32, row major, i32::MAX where unreachable
+//   next hops    n * n u32, row major, u32::MAX where there is no path
+
+use std::path::Path;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::fs::File;
+
+use Graph;
+
+const MAGIC: &'static [u8; 8] = b"APSPORCL";
+const VERSION: u16 = 1;
+const WEIGHT_I32: u8 = 1;
+const HAS_NEXT: u8 = 1;
+const HEADER: u64 = 28;
+const NO_HOP: u32 = std::u32::MAX;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceOracle {
+    size: usize,
+    dist: Vec<i32>,
+    next: Option<Vec<u32>>,
+}
+
+struct Checksum(u64);
+
+impl Checksum {
+    fn new() -> Checksum {
+        Checksum(0xcbf29ce484222325)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_bytes<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<(), Error> {
+    r.read_exact(buf)
+}
+
+// Exactly length bytes into buf, replacing what it held.
+fn read_section<R: Read>(r: &mut R, length: u64, buf: &mut Vec<u8>) -> Result<(), Error> {
+    buf.clear();
+
+    try!(r.take(length).read_to_end(buf));
+
+    if (buf.len() as u64) < length {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "truncated oracle"));
+    }
+
+    Ok(())
+}
+
+struct Header {
+    size: usize,
+    has_next: bool,
+    checksum: u64,
+}
+
+impl Header {
+    // The bytes of each matrix, or an error if the size is too large to be real.
+    fn section(&self) -> Result<u64, Error> {
+        (self.size as u64).checked_mul(self.size as u64)
+            .and_then(|cells| cells.checked_mul(4))
+            .ok_or_else(|| invalid("size out of range"))
+    }
+
+    fn payload(&self) -> Result<u64, Error> {
+        let section = try!(self.section());
+
+        section.checked_mul(if self.has_next { 2 } else { 1 }).ok_or_else(|| invalid("size out of range"))
+    }
+}
+
+fn read_header<R: Read>(r: &mut R) -> Result<Header, Error> {
+    let mut buf = [0; HEADER as usize];
+
+    try!(read_bytes(r, &mut buf));
+
+    if &buf[0..8] != MAGIC {
+        return Err(invalid("not a distance oracle"));
+    }
+
+    let word = |i: usize| {
+        let mut b = [0; 8];
+
+        b.copy_from_slice(&buf[i..i + 8]);
+        u64::from_le_bytes(b)
+    };
+
+    if u16::from_le_bytes([buf[8], buf[9]]) != VERSION {
+        return Err(invalid("unsupported version"));
+    }
+
+    if buf[10] != WEIGHT_I32 {
+        return Err(invalid("unsupported weight type"));
+    }
+
+    Ok(Header{size: word(12) as usize,
+              has_next: buf[11] & HAS_NEXT != 0,
+              checksum: word(20)})
+}
+
+impl DistanceOracle {
+    // Floyd-Warshall keeping the next hop of every shortest path.
+    pub fn compute(g: &Graph) -> Result<DistanceOracle, &'static str> {
+        let n = g.vertices.len();
+        let inf = std::i32::MAX;
+        let mut dist = vec![inf; n * n];
+        let mut next = vec![NO_HOP; n * n];
+
+        for i in 0..n {
+            dist[i * n + i] = 0;
+            next[i * n + i] = i as u32;
+        }
+
+        for v in &g.vertices {
+            let i = v.label as usize;
+
+            for e in &v.edges {
+                if e.weight < dist[i * n + e.tail] {
+                    dist[i * n + e.tail] = e.weight;
+                    next[i * n + e.tail] = e.tail as u32;
+                }
+            }
+        }
+
+        for k in 0..n {
+            for i in 0..n {
+                let ik = dist[i * n + k];
+
+                if ik == inf {
+                    continue;
+                }
+
+                for j in 0..n {
+                    let kj = dist[k * n + j];
+
+                    if kj != inf && ik + kj < dist[i * n + j] {
+                        dist[i * n + j] = ik + kj;
+                        next[i * n + j] = next[i * n + k];
+                    }
+                }
+            }
+
+            if dist[k * n + k] < 0 {
+                return Err("Negative cycle");
+            }
+        }
+
+        if (0..n).any(|i| dist[i * n + i] < 0) {
+            return Err("Negative cycle");
+        }
+
+        Ok(DistanceOracle{size: n, dist: dist, next: Some(next)})
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn distance(&self, from: usize, to: usize) -> Option<i32> {
+        match self.dist[from * self.size + to] {
+            std::i32::MAX => None,
+            d => Some(d),
+        }
+    }
+
+    // The smallest distance between any pair of vertices, as apsp() reports it.
+    pub fn smallest(&self) -> Option<i32> {
+        self.dist.iter().filter(|&&d| d != std::i32::MAX).cloned().min()
+    }
+
+    // The shortest path, if next hops were kept.
+    pub fn path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let next = match self.next {
+            Some(ref next) => next,
+            None => return None,
+        };
+
+        if next[from * self.size + to] == NO_HOP {
+            return None;
+        }
+
+        let mut path = vec![from];
+        let mut current = from;
+
+        // A shortest path has fewer than size hops, anything longer is a cycle.
+        while current != to {
+            if path.len() > self.size {
+                return None;
+            }
+
+            match next[current * self.size + to] {
+                NO_HOP => return None,
+                hop => current = hop as usize,
+            }
+
+            path.push(current);
+        }
+
+        Some(path)
+    }
+
+    // Drops the next hops, halving the size on disk.
+    pub fn without_paths(mut self) -> DistanceOracle {
+        self.next = None;
+        self
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> Result<(), Error> {
+        let mut payload: Vec<u8> = Vec::with_capacity(self.dist.len() * 8);
+
+        for &d in &self.dist {
+            payload.extend_from_slice(&d.to_le_bytes());
+        }
+
+        if let Some(ref next) = self.next {
+            for &h in next {
+                payload.extend_from_slice(&h.to_le_bytes());
+            }
+        }
+
+        let mut checksum = Checksum::new();
+
+        checksum.update(&payload);
+
+        try!(out.write_all(MAGIC));
+        try!(out.write_all(&VERSION.to_le_bytes()));
+        try!(out.write_all(&[WEIGHT_I32, if self.next.is_some() { HAS_NEXT } else { 0 }]));
+        try!(out.write_all(&(self.size as u64).to_le_bytes()));
+        try!(out.write_all(&checksum.0.to_le_bytes()));
+        out.write_all(&payload)
+    }
+
+    pub fn read<R: Read>(r: &mut R) -> Result<DistanceOracle, Error> {
+        let header = try!(read_header(r));
+        let section = try!(header.section());
+        let mut checksum = Checksum::new();
+        let mut buf = vec![];
+
+        // Read through take() so the buffer only grows as far as the data really goes.
+        try!(read_section(r, section, &mut buf));
+        checksum.update(&buf);
+
+        let dist = buf.chunks(4).map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+        let next = if header.has_next {
+            try!(read_section(r, section, &mut buf));
+            checksum.update(&buf);
+
+            let hops: Vec<u32> = buf.chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+
+            if hops.iter().any(|&h| h != NO_HOP && h as usize >= header.size) {
+                return Err(invalid("next hop out of range"));
+            }
+
+            Some(hops)
+        } else {
+            None
+        };
+
+        if checksum.0 != header.checksum {
+            return Err(invalid("checksum mismatch"));
+        }
+
+        Ok(DistanceOracle{size: header.size, dist: dist, next: next})
+    }
+
+    pub fn to_file(&self, path: &Path) -> Result<(), Error> {
+        let file = try!(File::create(path));
+        let mut buffer = BufWriter::new(file);
+
+        try!(self.write(&mut buffer));
+        buffer.flush()
+    }
+
+    pub fn from_file(path: &Path) -> Result<DistanceOracle, Error> {
+        let file = try!(File::open(path));
+
+        DistanceOracle::read(&mut BufReader::new(file))
+    }
+}
+
+// Answers distance queries straight from an oracle file without loading the matrices.
+// The checksum is verified once when the file is opened.
+#[derive(Debug)]
+pub struct OracleFile {
+    file: File,
+    size: usize,
+    has_next: bool,
+}
+
+impl OracleFile {
+    pub fn open(path: &Path) -> Result<OracleFile, Error> {
+        let file = try!(File::open(path));
+        let length = try!(file.metadata()).len();
+        let mut reader = BufReader::new(file);
+        let header = try!(read_header(&mut reader));
+        let payload = try!(header.payload());
+
+        if payload != length - HEADER {
+            return Err(invalid("size does not match the file"));
+        }
+
+        let mut checksum = Checksum::new();
+        let mut remaining = payload as usize;
+        let mut buf = [0; 8192];
+
+        while remaining > 0 {
+            let take = std::cmp::min(remaining, buf.len());
+
+            try!(read_bytes(&mut reader, &mut buf[..take]));
+            checksum.update(&buf[..take]);
+            remaining -= take;
+        }
+
+        if checksum.0 != header.checksum {
+            return Err(invalid("checksum mismatch"));
+        }
+
+        Ok(OracleFile{file: reader.into_inner(), size: header.size, has_next: header.has_next})
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn cell(&mut self, section: usize, from: usize, to: usize) -> Result<u32, Error> {
+        if from >= self.size || to >= self.size {
+            return Err(Error::new(ErrorKind::InvalidInput, "vertex out of range"));
+        }
+
+        let offset = HEADER + 4 * (section * self.size * self.size + from * self.size + to) as u64;
+        let mut buf = [0; 4];
+
+        try!(self.file.seek(SeekFrom::Start(offset)));
+        try!(read_bytes(&mut self.file, &mut buf));
+
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    pub fn distance(&mut self, from: usize, to: usize) -> Result<Option<i32>, Error> {
+        let d = try!(self.cell(0, from, to)) as i32;
+
+        Ok(if d == std::i32::MAX { None } else { Some(d) })
+    }
+
+    pub fn path(&mut self, from: usize, to: usize) -> Result<Option<Vec<usize>>, Error> {
+        if !self.has_next || try!(self.cell(1, from, to)) == NO_HOP {
+            return Ok(None);
+        }
+
+        let mut path = vec![from];
+        let mut current = from;
+
+        while current != to {
+            if path.len() > self.size {
+                return Err(invalid("next hops form a cycle"));
+            }
+
+            let hop = try!(self.cell(1, current, to));
+
+            if hop == NO_HOP || hop as usize >= self.size {
+                return Err(invalid("next hop out of range"));
+            }
+
+            current = hop as usize;
+            path.push(current);
+        }
+
+        Ok(Some(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+    use Graph;
+    use apsp;
+
+    #[test]
+    fn compute() {
+        let mut g = Graph::from_file(Path::new("g_simple1.txt")).ok().unwrap();
+        let o = DistanceOracle::compute(&g).ok().unwrap();
+
+        assert_eq!(o.size(), 6);
+        assert_eq!(o.distance(0, 4), Some(-6));
+        assert_eq!(o.distance(0, 5), None);
+        assert_eq!(o.path(0, 4), Some(vec![0, 1, 2, 4]));
+        assert_eq!(o.path(3, 0), None);
+        assert_eq!(o.smallest(), apsp(&mut g).ok());
+
+        let g = Graph::from_file(Path::new("g_simple2.txt")).ok().unwrap();
+
+        assert!(DistanceOracle::compute(&g).is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let g = Graph::from_file(Path::new("g_simple3.txt")).ok().unwrap();
+        let o = DistanceOracle::compute(&g).ok().unwrap();
+        let mut out: Vec<u8> = vec![];
+
+        o.write(&mut out).ok().unwrap();
+
+        assert_eq!(out.len(), 28 + 6 * 6 * 8);
+        assert_eq!(DistanceOracle::read(&mut &out[..]).ok().unwrap(), o);
+
+        let mut out: Vec<u8> = vec![];
+        let small = o.clone().without_paths();
+
+        small.write(&mut out).ok().unwrap();
+
+        assert_eq!(out.len(), 28 + 6 * 6 * 4);
+        assert_eq!(DistanceOracle::read(&mut &out[..]).ok().unwrap(), small);
+    }
+
+    #[test]
+    fn corrupted() {
+        let g = Graph::from_file(Path::new("g_simple1.txt")).ok().unwrap();
+        let o = DistanceOracle::compute(&g).ok().unwrap();
+        let mut out: Vec<u8> = vec![];
+
+        o.write(&mut out).ok().unwrap();
+        out[40] ^= 1;
+
+        assert!(DistanceOracle::read(&mut &out[..]).is_err());
+        assert!(DistanceOracle::read(&mut &out[..20]).is_err());
+        assert!(DistanceOracle::read(&mut &b"not an oracle at all, nope."[..]).is_err());
+
+        // A size whose matrix does not fit in 64 bits.
+        out[12..20].copy_from_slice(&(1u64 << 33).to_le_bytes());
+        assert_eq!(DistanceOracle::read(&mut &out[..]).err().unwrap().kind(), ErrorKind::InvalidData);
+
+        // A size far beyond the data that follows.
+        out[12..20].copy_from_slice(&(1u64 << 20).to_le_bytes());
+        assert!(DistanceOracle::read(&mut &out[..]).is_err());
+    }
+
+    #[test]
+    fn bad_next_hops() {
+        let g = Graph::from_file(Path::new("g_simple1.txt")).ok().unwrap();
+        let mut o = DistanceOracle::compute(&g).ok().unwrap();
+        let n = o.size();
+        let mut out: Vec<u8> = vec![];
+
+        // Checksummed correctly, so only the range check can catch it.
+        o.next.as_mut().unwrap()[3] = 99;
+        o.write(&mut out).ok().unwrap();
+        assert_eq!(DistanceOracle::read(&mut &out[..]).err().unwrap().kind(), ErrorKind::InvalidData);
+
+        // 0 and 1 each send the other towards 4.
+        let mut o = DistanceOracle::compute(&g).ok().unwrap();
+
+        o.next.as_mut().unwrap()[4] = 1;
+        o.next.as_mut().unwrap()[n + 4] = 0;
+        assert_eq!(o.path(0, 4), None);
+
+        let p = env::temp_dir().join(format!("apsp_oracle_bad_next_hops-{}.bin", std::process::id()));
+
+        o.to_file(&p).ok().unwrap();
+
+        let mut f = OracleFile::open(&p).ok().unwrap();
+
+        assert_eq!(f.path(0, 4).err().unwrap().kind(), ErrorKind::InvalidData);
+
+        fs::remove_file(&p).ok().unwrap();
+    }
+
+    #[test]
+    fn file_queries() {
+        let g = Graph::from_file(Path::new("g_simple3.txt")).ok().unwrap();
+        let o = DistanceOracle::compute(&g).ok().unwrap();
+        let p = env::temp_dir().join(format!("apsp_oracle_file_queries-{}.bin", std::process::id()));
+
+        o.to_file(&p).ok().unwrap();
+
+        assert_eq!(DistanceOracle::from_file(&p).ok().unwrap(), o);
+
+        let mut f = OracleFile::open(&p).ok().unwrap();
+
+        for i in 0..6 {
+            for j in 0..6 {
+                assert_eq!(f.distance(i, j).ok().unwrap(), o.distance(i, j));
+                assert_eq!(f.path(i, j).ok().unwrap(), o.path(i, j));
+            }
+        }
+
+        assert!(f.distance(6, 0).is_err());
+
+        let mut bytes = fs::read(&p).ok().unwrap();
+
+        bytes[12..20].copy_from_slice(&(1u64 << 33).to_le_bytes());
+        fs::write(&p, &bytes).ok().unwrap();
+        assert_eq!(OracleFile::open(&p).err().unwrap().kind(), ErrorKind::InvalidData);
+
+        bytes[12..20].copy_from_slice(&7u64.to_le_bytes());
+        fs::write(&p, &bytes).ok().unwrap();
+        assert_eq!(OracleFile::open(&p).err().unwrap().kind(), ErrorKind::InvalidData);
+
+        fs::remove_file(&p).ok().unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+use Graph;
+use dijkstra;
+use dijkstra::Exclusions;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub vertices: Vec<usize>,
+    pub cost: i32,
+}
+
+// Cost of walking the given vertex sequence, taking the cheapest of any parallel edges.
+pub fn path_cost(g: &Graph, vertices: &[usize]) -> Option<i32> {
+    let mut cost = 0;
+
+    for pair in vertices.windows(2) {
+        let cheapest = g.vertices[pair[0]].edges.iter()
+                                              .filter(|e| e.tail == pair[1])
+                                              .map(|e| e.weight)
+                                              .min();
+
+        match cheapest {
+            Some(w) => cost += w,
+            None => return None,
+        }
+    }
+
+    Some(cost)
+}
+
+// Yen's algorithm. Returns up to k loopless paths from source to target in
+// non-decreasing order of cost. Negative edges are supported via Johnson's
+// reweighting, so every spur search is a plain Dijkstra.
+pub fn k_shortest_paths(g: &Graph, source: usize, target: usize, k: usize) -> Result<Vec<Route>, &'static str> {
+    let h = try!(dijkstra::potentials(g));
+    let mut found: Vec<Route> = vec![];
+    let mut candidates: Vec<Route> = vec![];
+
+    if k == 0 {
+        return Ok(found);
+    }
+
+    match dijkstra::path_between(g, source, target, &h, &Exclusions::none(g.vertices.len())) {
+        Some((vertices, cost)) => found.push(Route{vertices: vertices, cost: cost}),
+        None => return Ok(found),
+    }
+
+    while found.len() < k {
+        let previous = found[found.len() - 1].vertices.clone();
+
+        for i in 0..(previous.len() - 1) {
+            let spur = previous[i];
+            let root = &previous[..(i + 1)];
+            let mut ex = Exclusions::none(g.vertices.len());
+
+            // Stop the spur path from retracing any known path that shares this root.
+            for r in &found {
+                if r.vertices.len() > i + 1 && &r.vertices[..(i + 1)] == root {
+                    ex.edges.insert((r.vertices[i], r.vertices[i + 1]));
+                }
+            }
+
+            // Keep the result loopless.
+            for &v in &root[..i] {
+                ex.vertices[v] = true;
+            }
+
+            if let Some((spur_path, _)) = dijkstra::path_between(g, spur, target, &h, &ex) {
+                let mut vertices = root[..i].to_vec();
+
+                vertices.extend(spur_path);
+
+                if found.iter().chain(candidates.iter()).all(|r| r.vertices != vertices) {
+                    let cost = path_cost(g, &vertices).unwrap();
+
+                    candidates.push(Route{vertices: vertices, cost: cost});
+                }
+            }
+        }
+
+        if candidates.is_empty() {
+            break;
+        }
+
+        let mut best = 0;
+
+        for (n, c) in candidates.iter().enumerate() {
+            let b = &candidates[best];
+
+            if (c.cost, c.vertices.len()) < (b.cost, b.vertices.len()) {
+                best = n;
+            }
+        }
+
+        found.push(candidates.swap_remove(best));
+    }
+
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use Graph;
+
+    // The classic example from Yen's paper, vertices C..H as 0..5.
+    fn yen_example() -> Graph {
+        let mut g = Graph::new();
+
+        g.build(6);
+
+        for &(h, t, w) in &[(0, 1, 3), (0, 2, 2), (1, 3, 4), (2, 1, 1), (2, 3, 2),
+                            (2, 4, 3), (3, 4, 2), (3, 5, 1), (4, 5, 2)] {
+            g.add_edge(h, t, w);
+        }
+
+        g
+    }
+
+    #[test]
+    fn yen() {
+        let g = yen_example();
+        let routes = k_shortest_paths(&g, 0, 5, 3).ok().unwrap();
+
+        assert_eq!(routes.len(), 3);
+        assert_eq!(routes[0], Route{vertices: vec![0, 2, 3, 5], cost: 5});
+        assert_eq!(routes[1].cost, 7);
+        assert_eq!(routes[2].cost, 8);
+    }
+
+    #[test]
+    fn exhausts_simple_paths() {
+        let g = yen_example();
+        let routes = k_shortest_paths(&g, 0, 5, 100).ok().unwrap();
+
+        // Every simple path from C to H.
+        assert_eq!(routes.len(), 7);
+
+        for pair in routes.windows(2) {
+            assert!(pair[0].cost <= pair[1].cost);
+        }
+
+        for r in &routes {
+            let mut seen = r.vertices.clone();
+
+            seen.sort();
+            seen.dedup();
+
+            assert_eq!(seen.len(), r.vertices.len());
+            assert_eq!(path_cost(&g, &r.vertices), Some(r.cost));
+        }
+    }
+
+    #[test]
+    fn negative_weights() {
+        let g = Graph::from_file(Path::new("g_simple1.txt")).ok().unwrap();
+        let routes = k_shortest_paths(&g, 0, 3, 5).ok().unwrap();
+
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0], Route{vertices: vec![0, 1, 2, 3], cost: -1});
+
+        assert!(k_shortest_paths(&g, 0, 5, 5).ok().unwrap().is_empty());
+    }
+
+    #[test]
+    fn negative_cycle() {
+        let g = Graph::from_file(Path::new("g_simple2.txt")).ok().unwrap();
+
+        assert!(k_shortest_paths(&g, 0, 3, 2).is_err());
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use Graph;
+
+// Tarjan's strongly connected components, iteratively so deep graphs cannot overflow the
+// stack. Returns the number of components and the component of each vertex. Components
+// are numbered in reverse topological order: an edge between different components always
+// runs from a higher number to a lower one.
+pub fn tarjan(g: &Graph) -> (usize, Vec<usize>) {
+    let len = g.vertices.len();
+    let unvisited = std::usize::MAX;
+    let mut index = vec![unvisited; len];
+    let mut low = vec![0; len];
+    let mut on_stack = vec![false; len];
+    let mut component = vec![unvisited; len];
+    let mut stack: Vec<usize> = vec![];
+    let mut count = 0;
+    let mut next = 0;
+
+    for root in 0..len {
+        if index[root] != unvisited {
+            continue;
+        }
+
+        // Each frame is a vertex and the position of the next edge to look at.
+        let mut frames: Vec<(usize, usize)> = vec![(root, 0)];
+
+        index[root] = next;
+        low[root] = next;
+        next += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(&(v, i)) = frames.last() {
+            if i < g.vertices[v].edges.len() {
+                let w = g.vertices[v].edges[i].tail;
+                let top = frames.len() - 1;
+
+                frames[top].1 += 1;
+
+                if index[w] == unvisited {
+                    index[w] = next;
+                    low[w] = next;
+                    next += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    frames.push((w, 0));
+                } else if on_stack[w] && index[w] < low[v] {
+                    low[v] = index[w];
+                }
+
+                continue;
+            }
+
+            frames.pop();
+
+            if let Some(&(parent, _)) = frames.last() {
+                if low[v] < low[parent] {
+                    low[parent] = low[v];
+                }
+            }
+
+            if low[v] == index[v] {
+                loop {
+                    let w = stack.pop().unwrap();
+
+                    on_stack[w] = false;
+                    component[w] = count;
+
+                    if w == v {
+                        break;
+                    }
+                }
+
+                count += 1;
+            }
+        }
+    }
+
+    (count, component)
+}
+
+// Kosaraju's strongly connected components, tracking visits with each vertex's explored
+// flag. As with tarjan, components are numbered in reverse topological order.
+pub fn kosaraju(g: &mut Graph) -> (usize, Vec<usize>) {
+    let len = g.vertices.len();
+    let mut finished: Vec<usize> = Vec::with_capacity(len);
+
+    for v in &mut g.vertices {
+        v.explored = false;
+    }
+
+    // First pass: order vertices by finishing time on the reversed graph.
+    let r = g.reversed();
+    let mut seen = vec![false; len];
+
+    for root in 0..len {
+        if seen[root] {
+            continue;
+        }
+
+        let mut frames: Vec<(usize, usize)> = vec![(root, 0)];
+
+        seen[root] = true;
+
+        while let Some(&(v, i)) = frames.last() {
+            if i < r.vertices[v].edges.len() {
+                let w = r.vertices[v].edges[i].tail;
+                let top = frames.len() - 1;
+
+                frames[top].1 += 1;
+
+                if !seen[w] {
+                    seen[w] = true;
+                    frames.push((w, 0));
+                }
+            } else {
+                finished.push(v);
+                frames.pop();
+            }
+        }
+    }
+
+    // Second pass: sweep the original graph in reverse finishing order. Each sweep picks up
+    // exactly one component, sinks first.
+    let mut component = vec![0; len];
+    let mut count = 0;
+
+    for &root in finished.iter().rev() {
+        if g.vertices[root].explored {
+            continue;
+        }
+
+        let mut stack = vec![root];
+
+        g.vertices[root].explored = true;
+
+        while let Some(v) = stack.pop() {
+            component[v] = count;
+
+            for i in 0..g.vertices[v].edges.len() {
+                let w = g.vertices[v].edges[i].tail;
+
+                if !g.vertices[w].explored {
+                    g.vertices[w].explored = true;
+                    stack.push(w);
+                }
+            }
+        }
+
+        count += 1;
+    }
+
+    (count, component)
+}
+
+// Kahn's algorithm. Fails if the graph has a cycle.
+pub fn topological_order(g: &Graph) -> Result<Vec<usize>, &'static str> {
+    let len = g.vertices.len();
+    let mut indegree = vec![0; len];
+    let mut order = Vec::with_capacity(len);
+
+    for v in &g.vertices {
+        for e in &v.edges {
+            indegree[e.tail] += 1;
+        }
+    }
+
+    let mut ready: VecDeque<usize> = (0..len).filter(|&v| indegree[v] == 0).collect();
+
+    while let Some(v) = ready.pop_front() {
+        order.push(v);
+
+        for e in &g.vertices[v].edges {
+            indegree[e.tail] -= 1;
+
+            if indegree[e.tail] == 0 {
+                ready.push_back(e.tail);
+            }
+        }
+    }
+
+    if order.len() < len {
+        return Err("Cycle");
+    }
+
+    Ok(order)
+}
+
+// The DAG of strongly connected components. Components are numbered in topological order,
+// so every edge of the DAG runs from a lower number to a higher one. Parallel edges between
+// two components are merged, keeping the cheapest.
+#[derive(Debug)]
+pub struct Condensation {
+    pub component: Vec<usize>,
+    pub members: Vec<Vec<usize>>,
+    pub dag: Graph,
+}
+
+impl Condensation {
+    pub fn new(g: &Graph) -> Condensation {
+        let (count, reverse) = tarjan(g);
+        let component: Vec<usize> = reverse.iter().map(|&c| count - 1 - c).collect();
+        let mut members = vec![vec![]; count];
+        let mut cheapest: Vec<HashMap<usize, i32>> = vec![HashMap::new(); count];
+        let mut dag = Graph::new();
+
+        dag.build(count as i32);
+
+        for v in &g.vertices {
+            let c = component[v.label as usize];
+
+            members[c].push(v.label as usize);
+
+            for e in &v.edges {
+                let d = component[e.tail];
+
+                if c == d {
+                    continue;
+                }
+
+                let w = cheapest[c].entry(d).or_insert(e.weight);
+
+                if e.weight < *w {
+                    *w = e.weight;
+                }
+            }
+        }
+
+        for (c, out) in cheapest.iter().enumerate() {
+            let mut tails: Vec<(&usize, &i32)> = out.iter().collect();
+
+            tails.sort();
+
+            for (&d, &w) in tails {
+                dag.add_edge(c, d, w);
+            }
+        }
+
+        Condensation{component: component, members: members, dag: dag}
+    }
+
+    pub fn components(&self) -> usize {
+        self.members.len()
+    }
+
+    // Components in topological order, which is simply their numbering.
+    pub fn order(&self) -> Vec<usize> {
+        (0..self.members.len()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use Graph;
+
+    // Components renumbered in order of their lowest vertex, so labellings of the same
+    // partition compare equal.
+    fn partition(component: &[usize]) -> Vec<usize> {
+        let mut id = vec![std::usize::MAX; component.len()];
+        let mut next = 0;
+
+        component.iter().map(|&c| {
+            if id[c] == std::usize::MAX {
+                id[c] = next;
+                next += 1;
+            }
+
+            id[c]
+        }).collect()
+    }
+
+    #[test]
+    fn components() {
+        let mut g = Graph::new();
+
+        g.build(6);
+
+        for &(h, t) in &[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 3), (5, 5)] {
+            g.add_edge(h, t, 1);
+        }
+
+        let (count, component) = tarjan(&g);
+
+        assert_eq!(count, 3);
+        assert_eq!(component[0], component[1]);
+        assert_eq!(component[1], component[2]);
+        assert_eq!(component[3], component[4]);
+        assert!(component[2] > component[3]);
+        assert!(component[5] != component[0] && component[5] != component[3]);
+    }
+
+    #[test]
+    fn kosaraju_matches_tarjan() {
+        let mut g = Graph::from_file(Path::new("g1.txt")).ok().unwrap();
+        let (count, component) = tarjan(&g);
+        let (other_count, other) = kosaraju(&mut g);
+
+        assert_eq!(other_count, count);
+        assert_eq!(partition(&other), partition(&component));
+        assert!(g.vertices.iter().all(|v| v.explored));
+    }
+
+    #[test]
+    fn condensation() {
+        let mut g = Graph::new();
+
+        g.build(6);
+
+        for &(h, t, w) in &[(0, 1, 1), (1, 0, 1), (1, 2, 5), (0, 2, 3), (2, 3, 1), (3, 2, 1), (4, 0, 2)] {
+            g.add_edge(h, t, w);
+        }
+
+        let c = Condensation::new(&g);
+
+        assert_eq!(c.components(), 4);
+        assert_eq!(c.component[0], c.component[1]);
+        assert_eq!(c.component[2], c.component[3]);
+        assert_eq!(c.members[c.component[2]], vec![2, 3]);
+
+        // Vertex 4 feeds {0, 1}, which feeds {2, 3}.
+        assert!(c.component[4] < c.component[0]);
+        assert!(c.component[0] < c.component[2]);
+
+        let ref edges = c.dag.vertices[c.component[0]].edges;
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].weight, 3);
+
+        let order = topological_order(&c.dag).ok().unwrap();
+        let position: Vec<usize> = (0..order.len()).map(|v| order.iter().position(|&o| o == v).unwrap()).collect();
+
+        for v in &c.dag.vertices {
+            for e in &v.edges {
+                assert!(position[v.label as usize] < position[e.tail]);
+                assert!((v.label as usize) < e.tail);
+            }
+        }
+
+        assert!(topological_order(&g).is_err());
+    }
+
+    #[test]
+    fn long_chain() {
+        let mut g = Graph::new();
+        let size = 100000;
+
+        g.build(size as i32);
+
+        for v in 0..(size - 1) {
+            g.add_edge(v, v + 1, 1);
+        }
+
+        let (count, component) = tarjan(&g);
+
+        assert_eq!(count, size);
+        assert_eq!(component[0], size - 1);
+        assert_eq!(component[size - 1], 0);
+
+        let (other_count, other) = kosaraju(&mut g);
+
+        assert_eq!(other_count, count);
+        assert_eq!(partition(&other), partition(&component));
+        assert_eq!(topological_order(&g).ok().unwrap(), (0..size).collect::<Vec<usize>>());
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::Path;
+use std::io::BufReader;
+use std::io::BufRead;
+use std::fs::File;
+
+use Graph;
+use scc;
+
+// 2-SAT over variables 1..n. A literal is a variable number, negated for its complement,
+// and each clause (a, b) asks for a or b to hold.
+#[derive(Debug, Clone)]
+pub struct TwoSat {
+    variables: usize,
+    clauses: Vec<(i32, i32)>,
+}
+
+impl TwoSat {
+    pub fn new(variables: usize) -> TwoSat {
+        TwoSat{variables: variables, clauses: vec![]}
+    }
+
+    pub fn add_clause(&mut self, a: i32, b: i32) {
+        for &l in &[a, b] {
+            if l == 0 || l.abs() as usize > self.variables {
+                panic!("Invalid literal: {:?}", l);
+            }
+        }
+
+        self.clauses.push((a, b));
+    }
+
+    pub fn variables(&self) -> usize {
+        self.variables
+    }
+
+    pub fn clauses(&self) -> &[(i32, i32)] {
+        &self.clauses
+    }
+
+    // Reads the course format: the number of variables (and clauses), then one "x y" clause
+    // per line.
+    pub fn from_file(path: &Path) -> Result<TwoSat, std::io::Error> {
+        let file = try!(File::open(path));
+        let mut buffer = BufReader::new(&file);
+        let mut first_line = String::new();
+
+        try!(buffer.read_line(&mut first_line));
+
+        let details = Graph::split_line::<usize>(first_line.trim());
+        let mut sat = TwoSat::new(details[0]);
+
+        for l in buffer.lines() {
+            match l {
+                Ok(parts) => {
+                    if parts.trim().is_empty() {
+                        continue;
+                    }
+
+                    let details = Graph::split_line::<i32>(parts.trim());
+
+                    sat.add_clause(details[0], details[1]);
+                },
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(sat)
+    }
+
+    // Vertex 2(i - 1) is literal i and vertex 2(i - 1) + 1 is its negation.
+    pub fn vertex(literal: i32) -> usize {
+        let v = (literal.abs() - 1) as usize * 2;
+
+        if literal > 0 { v } else { v + 1 }
+    }
+
+    // Each clause (a, b) becomes the implications !a -> b and !b -> a.
+    pub fn implication_graph(&self) -> Graph {
+        let mut g = Graph::new();
+
+        g.build(2 * self.variables as i32);
+
+        for &(a, b) in &self.clauses {
+            g.add_edge(TwoSat::vertex(-a), TwoSat::vertex(b), 0);
+            g.add_edge(TwoSat::vertex(-b), TwoSat::vertex(a), 0);
+        }
+
+        g
+    }
+
+    // A satisfying assignment, indexed by variable - 1, or None if there is none. The
+    // formula is unsatisfiable exactly when a literal and its negation share a component.
+    pub fn solve(&self) -> Option<Vec<bool>> {
+        let g = self.implication_graph();
+        let (_, component) = scc::tarjan(&g);
+        let mut assignment = Vec::with_capacity(self.variables);
+
+        for i in 0..self.variables {
+            let (t, f) = (component[2 * i], component[2 * i + 1]);
+
+            if t == f {
+                return None;
+            }
+
+            // Components are numbered in reverse topological order. Taking whichever literal
+            // comes later never implies its own negation.
+            assignment.push(t < f);
+        }
+
+        Some(assignment)
+    }
+
+    pub fn satisfies(&self, assignment: &[bool]) -> bool {
+        let holds = |l: i32| assignment[(l.abs() - 1) as usize] == (l > 0);
+
+        self.clauses.iter().all(|&(a, b)| holds(a) || holds(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use rng::Rng;
+
+    #[test]
+    fn representation() {
+        let sat = TwoSat::from_file(Path::new("2sat_simple1.txt")).ok().unwrap();
+        let g = sat.implication_graph();
+
+        assert_eq!(sat.variables(), 4);
+        assert_eq!(sat.clauses()[1], (-1, 3));
+        assert_eq!(g.vertices.len(), 8);
+
+        // (-1 or 3) gives 1 -> 3 and -3 -> -1.
+        assert_eq!(g.vertices[TwoSat::vertex(1)].edges[0].tail, TwoSat::vertex(3));
+        assert_eq!(g.vertices[TwoSat::vertex(-3)].edges[0].tail, TwoSat::vertex(-1));
+    }
+
+    #[test]
+    fn simple1() {
+        let sat = TwoSat::from_file(Path::new("2sat_simple1.txt")).ok().unwrap();
+        let assignment = sat.solve().unwrap();
+
+        assert!(sat.satisfies(&assignment));
+    }
+
+    #[test]
+    fn simple2() {
+        let sat = TwoSat::from_file(Path::new("2sat_simple2.txt")).ok().unwrap();
+
+        assert!(sat.solve().is_none());
+    }
+
+    #[test]
+    fn brute_force() {
+        let mut rng = Rng::new(3);
+        let mut next = || rng.next() as i32;
+
+        for _ in 0..200 {
+            let variables = 1 + (next() % 6) as usize;
+            let mut sat = TwoSat::new(variables);
+
+            for _ in 0..(next() % 12) {
+                let mut literal = || {
+                    let v = 1 + next() % variables as i32;
+
+                    if next() % 2 == 0 { v } else { -v }
+                };
+                let (a, b) = (literal(), literal());
+
+                sat.add_clause(a, b);
+            }
+
+            let exists = (0..(1 << variables)).any(|bits: usize| {
+                let assignment: Vec<bool> = (0..variables).map(|i| bits & (1 << i) != 0).collect();
+
+                sat.satisfies(&assignment)
+            });
+
+            match sat.solve() {
+                Some(assignment) => assert!(sat.satisfies(&assignment)),
+                None => assert!(!exists),
+            }
+        }
+    }
+}
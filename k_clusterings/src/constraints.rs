@@ -0,0 +1,217 @@
+use Graph;
+use Clustering;
+use sorted_edges;
+use union_find::UnionFind;
+
+// Pairs of vertices that must share a cluster, and pairs that must not.
+#[derive(Debug, Clone, Default)]
+pub struct Constraints {
+    must: Vec<(usize, usize)>,
+    cannot: Vec<(usize, usize)>,
+}
+
+impl Constraints {
+    pub fn new() -> Constraints {
+        Constraints{must: vec![], cannot: vec![]}
+    }
+
+    pub fn must_link(&mut self, a: usize, b: usize) {
+        self.must.push((a, b));
+    }
+
+    pub fn cannot_link(&mut self, a: usize, b: usize) {
+        self.cannot.push((a, b));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.must.is_empty() && self.cannot.is_empty()
+    }
+}
+
+// Kruskal's algorithm as in cluster_spacing, with must-link pairs merged before the first
+// edge and any merge that would join a cannot-link pair skipped. The spacing is the cheapest
+// edge left between two clusters, skipped ones included. Fails if the constraints
+// contradict each other or rule out exactly the requested number of groups, if there are
+// fewer vertices than groups, or if the graph has too few edges to join that few.
+//
+// The cannot-link check is greedy, not exact: exact feasibility is NP-hard, and a skipped
+// merge can leave too many clusters even when some other partition into groups exists.
+pub fn constrained_spacing(groups: usize, g: &mut Graph, constraints: &Constraints) -> Result<Clustering, &'static str> {
+    let n = g.vertices.len();
+    let mut clusters = UnionFind::new(n);
+    let mut merged = vec![];
+
+    if groups > n {
+        return Err("More clusters requested than vertices");
+    }
+
+    for &(a, b) in constraints.must.iter().chain(constraints.cannot.iter()) {
+        if a >= n || b >= n {
+            return Err("Constraint on a missing vertex");
+        }
+    }
+
+    for &(a, b) in &constraints.must {
+        clusters.union(a, b);
+    }
+
+    // Each leader keeps the vertices its cluster must stay apart from.
+    let mut apart: Vec<Vec<usize>> = vec![vec![]; n];
+
+    for &(a, b) in &constraints.cannot {
+        if clusters.connected(a, b) {
+            return Err("Must-link and cannot-link pairs conflict");
+        }
+
+        let (la, lb) = (clusters.find(a), clusters.find(b));
+
+        apart[la].push(b);
+        apart[lb].push(a);
+    }
+
+    if clusters.components() < groups {
+        return Err("Must-link pairs leave fewer clusters than requested");
+    }
+
+    let edges = sorted_edges(g);
+
+    for e in &edges {
+        if clusters.components() <= groups {
+            break;
+        }
+
+        let (a, b) = (clusters.find(e.head), clusters.find(e.tail));
+
+        if a == b {
+            continue;
+        }
+
+        if apart[a].iter().any(|&x| clusters.find(x) == b) {
+            continue;
+        }
+
+        clusters.union(a, b);
+
+        let (root, other) = if clusters.find(a) == a { (a, b) } else { (b, a) };
+        let moved = std::mem::take(&mut apart[other]);
+
+        apart[root].extend(moved);
+        merged.push((e.head, e.tail, e.cost));
+    }
+
+    if clusters.components() > groups {
+        // Whether the graph could get there at all, cannot-link pairs aside.
+        let mut reachable = UnionFind::new(n);
+
+        for &(a, b) in &constraints.must {
+            reachable.union(a, b);
+        }
+
+        for e in &edges {
+            reachable.union(e.head, e.tail);
+        }
+
+        if reachable.components() > groups {
+            return Err("Too few edges to join the requested number of clusters");
+        }
+
+        return Err("Cannot-link pairs leave more clusters than requested");
+    }
+
+    let mut spacing = 0;
+
+    for e in &edges {
+        if !clusters.connected(e.head, e.tail) {
+            spacing = e.cost;
+            break;
+        }
+    }
+
+    let result = Clustering::new(&mut clusters, spacing, merged);
+
+    for v in &mut g.vertices {
+        v.leader = result.cluster[v.label as usize];
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use {Graph, cluster_spacing};
+    use fixtures::line;
+
+    #[test]
+    fn unconstrained() {
+        let p = Path::new("clustering1.txt");
+        let mut g = Graph::from_file(p).ok().unwrap();
+        let c = constrained_spacing(4, &mut g, &Constraints::new()).ok().unwrap();
+
+        assert_eq!(c, cluster_spacing(4, &mut g));
+    }
+
+    #[test]
+    fn must_link() {
+        let mut g = line(&[0, 1, 2, 10, 11, 30]);
+        let mut constraints = Constraints::new();
+
+        constraints.must_link(2, 3);
+
+        let c = constrained_spacing(2, &mut g, &constraints).ok().unwrap();
+
+        assert_eq!(c.members, vec![vec![0, 1, 2, 3, 4], vec![5]]);
+        assert_eq!(c.spacing, 19);
+    }
+
+    #[test]
+    fn cannot_link() {
+        let mut g = line(&[0, 1, 2, 10, 11, 30]);
+        let mut constraints = Constraints::new();
+
+        constraints.cannot_link(0, 2);
+
+        // 1 joins 0 first, so 2 has to go with 10 and 11.
+        let c = constrained_spacing(3, &mut g, &constraints).ok().unwrap();
+
+        assert_eq!(c.members, vec![vec![0, 1], vec![2, 3, 4], vec![5]]);
+        assert_eq!(c.spacing, 1);
+        assert_eq!(g.vertices[2].leader, 1);
+    }
+
+    #[test]
+    fn infeasible() {
+        let mut g = line(&[0, 1, 2, 10]);
+        let mut constraints = Constraints::new();
+
+        constraints.must_link(0, 1);
+        constraints.must_link(1, 2);
+
+        assert_eq!(constrained_spacing(3, &mut g, &constraints).err(), Some("Must-link pairs leave fewer clusters than requested"));
+        assert!(constrained_spacing(2, &mut g, &constraints).is_ok());
+        assert_eq!(constrained_spacing(5, &mut g, &Constraints::new()).err(), Some("More clusters requested than vertices"));
+
+        constraints.cannot_link(0, 2);
+        assert!(constrained_spacing(2, &mut g, &constraints).is_err());
+
+        let mut constraints = Constraints::new();
+
+        constraints.cannot_link(0, 1);
+        constraints.cannot_link(1, 2);
+        constraints.cannot_link(0, 2);
+
+        assert!(constrained_spacing(2, &mut g, &constraints).is_err());
+        assert!(constrained_spacing(3, &mut g, &constraints).is_ok());
+
+        // Two components can never become one, constraints or not.
+        let mut g = line(&[0, 1, 2, 10]);
+
+        for v in &mut g.vertices {
+            v.edges.retain(|e| (e.head < 2) == (e.tail < 2));
+        }
+
+        assert_eq!(constrained_spacing(1, &mut g, &Constraints::new()).err(), Some("Too few edges to join the requested number of clusters"));
+        assert!(constrained_spacing(2, &mut g, &Constraints::new()).is_ok());
+    }
+}
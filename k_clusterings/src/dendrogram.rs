@@ -0,0 +1,340 @@
+use std::io::Write;
+
+use Graph;
+use Clustering;
+use sorted_edges;
+use union_find::UnionFind;
+
+// One merge of two clusters. Following SciPy, the leaves are nodes 0..n and the i-th merge
+// creates node n + i. The edge joins a vertex of each side: for single linkage it is the
+// graph edge that joined them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merge {
+    pub left: usize,
+    pub right: usize,
+    pub height: f64,
+    pub size: usize,
+    pub edge: (usize, usize),
+}
+
+// The full history of single-linkage clustering, so it can be cut at any k or distance
+// without running Kruskal's again. A disconnected graph leaves one tree per component.
+#[derive(Debug, Clone)]
+pub struct Dendrogram {
+    leaves: usize,
+    merges: Vec<Merge>,
+}
+
+// A dendrogram cut into clusters. As Clustering, numbered in order of the lowest vertex, but
+// with heights kept exact: spacing is the height of the next merge, None if there is none.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cut {
+    pub cluster: Vec<usize>,
+    pub members: Vec<Vec<usize>>,
+    pub spacing: Option<f64>,
+    pub merged: Vec<(usize, usize, f64)>,
+}
+
+impl Cut {
+    pub fn clusters(&self) -> usize {
+        self.members.len()
+    }
+}
+
+enum Step {
+    Node(usize, Option<f64>),
+    Text(String),
+}
+
+impl Dendrogram {
+    pub fn single_linkage(g: &Graph) -> Dendrogram {
+        let leaves = g.vertices.len();
+        let mut clusters = UnionFind::new(leaves);
+        let mut node: Vec<usize> = (0..leaves).collect();
+        let mut merges = vec![];
+
+        for e in &sorted_edges(g) {
+            let (a, b) = (clusters.find(e.head), clusters.find(e.tail));
+
+            if a == b {
+                continue;
+            }
+
+            let (left, right) = (node[a], node[b]);
+
+            clusters.union(a, b);
+
+            let root = clusters.find(a);
+
+            node[root] = leaves + merges.len();
+            merges.push(Merge{
+                left: std::cmp::min(left, right),
+                right: std::cmp::max(left, right),
+                height: e.cost as f64,
+                size: clusters.size(root),
+                edge: (e.head, e.tail),
+            });
+        }
+
+        Dendrogram{leaves: leaves, merges: merges}
+    }
+
+    // Builds the dendrogram from the edges that joined clusters, (a, b, height) with a and b
+    // any vertex on either side, in whatever order they were found. Edges between vertices
+    // already joined are skipped. NaN heights, whatever their sign bit, become positive NaN
+    // so they sort after all others.
+    pub fn from_edges(leaves: usize, mut edges: Vec<(usize, usize, f64)>) -> Dendrogram {
+        for e in &mut edges {
+            if e.2.is_nan() {
+                e.2 = std::f64::NAN.abs();
+            }
+        }
+
+        edges.sort_by(|x, y| x.2.total_cmp(&y.2));
+
+        let mut clusters = UnionFind::new(leaves);
+        let mut node: Vec<usize> = (0..leaves).collect();
+        let mut merges = vec![];
+
+        for &(a, b, height) in &edges {
+            let (ra, rb) = (clusters.find(a), clusters.find(b));
+
+            if ra == rb {
+                continue;
+            }
+
+            let (left, right) = (node[ra], node[rb]);
+
+            clusters.union(ra, rb);
+
+            let root = clusters.find(a);
+
+            node[root] = leaves + merges.len();
+            merges.push(Merge{
+                left: std::cmp::min(left, right),
+                right: std::cmp::max(left, right),
+                height: height,
+                size: clusters.size(root),
+                edge: (a, b),
+            });
+        }
+
+        Dendrogram{leaves: leaves, merges: merges}
+    }
+
+    pub fn leaves(&self) -> usize {
+        self.leaves
+    }
+
+    pub fn merges(&self) -> &[Merge] {
+        &self.merges
+    }
+
+    // Applies the first count merges.
+    fn clustering(&self, count: usize) -> Cut {
+        let mut clusters = UnionFind::new(self.leaves);
+        let mut merged = vec![];
+
+        for m in &self.merges[..count] {
+            clusters.union(m.edge.0, m.edge.1);
+            merged.push((m.edge.0, m.edge.1, m.height));
+        }
+
+        // Only for its numbering of the clusters.
+        let numbered = Clustering::new(&mut clusters, 0, vec![]);
+
+        Cut{
+            cluster: numbered.cluster,
+            members: numbered.members,
+            spacing: self.merges.get(count).map(|m| m.height),
+            merged: merged,
+        }
+    }
+
+    // The clustering with k clusters, or as few as the graph allows.
+    pub fn cut(&self, k: usize) -> Cut {
+        let count = std::cmp::min(self.leaves.saturating_sub(k), self.merges.len());
+
+        self.clustering(count)
+    }
+
+    // The clustering with every merge at height at most distance applied.
+    pub fn cut_at(&self, distance: f64) -> Cut {
+        let count = self.merges.iter().take_while(|m| m.height <= distance).count();
+
+        self.clustering(count)
+    }
+
+    // Rows of (left, right, height, size), as scipy.cluster.hierarchy expects. A complete
+    // linkage matrix needs a connected graph.
+    pub fn linkage(&self) -> Vec<[f64; 4]> {
+        self.merges.iter()
+            .map(|m| [m.left as f64, m.right as f64, m.height, m.size as f64])
+            .collect()
+    }
+
+    // The linkage matrix as whitespace separated rows, readable by numpy.loadtxt.
+    pub fn write_linkage<W: Write>(&self, w: &mut W) -> Result<(), std::io::Error> {
+        for m in &self.merges {
+            try!(writeln!(w, "{} {} {} {}", m.left, m.right, m.height, m.size));
+        }
+
+        Ok(())
+    }
+
+    // Newick with vertices as leaf names and branch lengths the difference in height. The
+    // trees of a disconnected graph hang from one extra root.
+    pub fn newick(&self) -> String {
+        let total = self.leaves + self.merges.len();
+        let mut is_child = vec![false; total];
+
+        for m in &self.merges {
+            is_child[m.left] = true;
+            is_child[m.right] = true;
+        }
+
+        let roots: Vec<usize> = (0..total).filter(|&x| !is_child[x]).collect();
+        let mut out = String::new();
+
+        // Built with an explicit stack, since a chain of merges can be as deep as the graph.
+        let mut stack = vec![];
+
+        if roots.len() > 1 {
+            out.push('(');
+            stack.push(Step::Text(")".to_string()));
+        }
+
+        for (i, &r) in roots.iter().enumerate().rev() {
+            stack.push(Step::Node(r, None));
+
+            if i > 0 {
+                stack.push(Step::Text(",".to_string()));
+            }
+        }
+
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Text(t) => out.push_str(&t),
+                Step::Node(x, parent) => {
+                    let height = if x < self.leaves { 0.0 } else { self.merges[x - self.leaves].height };
+                    let length = match parent {
+                        Some(p) => format!(":{}", p - height),
+                        None => String::new(),
+                    };
+
+                    if x < self.leaves {
+                        out.push_str(&format!("{}{}", x, length));
+                        continue;
+                    }
+
+                    let ref m = self.merges[x - self.leaves];
+
+                    out.push('(');
+                    stack.push(Step::Text(format!("){}", length)));
+                    stack.push(Step::Node(m.right, Some(height)));
+                    stack.push(Step::Text(",".to_string()));
+                    stack.push(Step::Node(m.left, Some(height)));
+                },
+            }
+        }
+
+        out.push(';');
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use {Graph, Edge, cluster_spacing};
+
+    fn graph(size: i32, edges: &[(usize, usize, i32)]) -> Graph {
+        let mut g = Graph::new();
+
+        g.build(size);
+
+        for &(h, t, c) in edges {
+            g.vertices[h].edges.push(Edge{head: h, tail: t, cost: c});
+            g.vertices[t].edges.push(Edge{head: t, tail: h, cost: c});
+        }
+
+        g
+    }
+
+    #[test]
+    fn merges() {
+        let g = graph(4, &[(0, 1, 1), (2, 3, 2), (1, 2, 5), (0, 3, 9)]);
+        let d = Dendrogram::single_linkage(&g);
+
+        assert_eq!(d.leaves(), 4);
+        assert_eq!(d.merges().len(), 3);
+        assert_eq!(d.merges()[2], Merge{left: 4, right: 5, height: 5.0, size: 4, edge: (1, 2)});
+
+        assert_eq!(d.linkage()[1], [2.0, 3.0, 2.0, 2.0]);
+        assert_eq!(d.newick(), "((0:1,1:1):4,(2:2,3:2):3);");
+
+        let mut out = vec![];
+
+        d.write_linkage(&mut out).ok().unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "0 1 1 2\n2 3 2 2\n4 5 5 4\n");
+    }
+
+    #[test]
+    fn forest() {
+        let g = graph(3, &[(0, 1, 3)]);
+        let d = Dendrogram::single_linkage(&g);
+
+        assert_eq!(d.newick(), "(2,(0:3,1:3));");
+        assert_eq!(d.cut(1).clusters(), 2);
+    }
+
+    #[test]
+    fn from_edges() {
+        let nan = -std::f64::NAN.abs();
+        let d = Dendrogram::from_edges(4, vec![(2, 3, 4.0), (0, 3, nan), (1, 0, 1.0), (0, 1, 2.0), (3, 1, 6.0)]);
+        let heights: Vec<f64> = d.merges().iter().map(|m| m.height).collect();
+
+        assert_eq!(heights, vec![1.0, 4.0, 6.0]);
+        assert_eq!(d.merges()[2], Merge{left: 4, right: 5, height: 6.0, size: 4, edge: (3, 1)});
+
+        // Heights below one half are kept, not rounded to the no-merge spacing.
+        let d = Dendrogram::from_edges(3, vec![(0, 1, 0.25), (1, 2, 0.375)]);
+
+        assert_eq!(d.cut(2).spacing, Some(0.375));
+        assert_eq!(d.cut(1).merged, vec![(0, 1, 0.25), (1, 2, 0.375)]);
+    }
+
+    #[test]
+    fn cuts() {
+        let g = graph(4, &[(0, 1, 1), (2, 3, 2), (1, 2, 5), (0, 3, 9)]);
+        let d = Dendrogram::single_linkage(&g);
+
+        assert_eq!(d.cut_at(0.0).clusters(), 4);
+        assert_eq!(d.cut_at(2.0).clusters(), 2);
+        assert_eq!(d.cut_at(2.0).spacing, Some(5.0));
+        assert_eq!(d.cut_at(5.0).spacing, None);
+        assert_eq!(d.cut_at(5.0).clusters(), 1);
+        assert_eq!(d.cut(10).clusters(), 4);
+    }
+
+    #[test]
+    fn matches_cluster_spacing() {
+        let p = Path::new("clustering1.txt");
+        let mut g = Graph::from_file(p).ok().unwrap();
+        let d = Dendrogram::single_linkage(&g);
+
+        assert_eq!(d.merges().len(), g.vertices.len() - 1);
+        assert_eq!(d.merges().last().unwrap().size, g.vertices.len());
+
+        for &k in &[1, 2, 4, 10, 100, g.vertices.len()] {
+            let (cut, expected) = (d.cut(k), cluster_spacing(k, &mut g));
+
+            assert_eq!(cut.members, expected.members);
+            assert_eq!(cut.spacing.unwrap_or(0.0), expected.spacing as f64);
+        }
+
+        assert_eq!(d.cut(4).spacing, Some(106.0));
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::VecDeque;
+
+use dendrogram::Dendrogram;
+use points::{Coordinates, KdTree, reachability_mst};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Label {
+    Noise,
+    Cluster(usize),
+}
+
+// Clusters numbered 0..clusters, with every point either in one of them or noise.
+#[derive(Debug, Clone)]
+pub struct Density {
+    pub labels: Vec<Label>,
+    pub clusters: usize,
+}
+
+impl Density {
+    pub fn members(&self) -> Vec<Vec<usize>> {
+        let mut members = vec![vec![]; self.clusters];
+
+        for (x, l) in self.labels.iter().enumerate() {
+            if let Label::Cluster(j) = *l {
+                members[j].push(x);
+            }
+        }
+
+        members
+    }
+
+    pub fn noise(&self) -> Vec<usize> {
+        (0..self.labels.len()).filter(|&x| self.labels[x] == Label::Noise).collect()
+    }
+}
+
+// DBSCAN. A point with at least min_points points (itself included) within eps is a core
+// point; clusters are the core points reachable from one another through such
+// neighbourhoods, plus the border points within eps of them. The rest is noise.
+pub fn dbscan(c: &Coordinates, eps: f64, min_points: usize) -> Density {
+    let n = c.len();
+    let tree = KdTree::new(c);
+    let neighbours: Vec<Vec<usize>> = (0..n).map(|x| tree.within(c, c.point(x), eps)).collect();
+    let mut labels = vec![Label::Noise; n];
+    let mut visited = vec![false; n];
+    let mut clusters = 0;
+
+    for root in 0..n {
+        if visited[root] || neighbours[root].len() < min_points {
+            continue;
+        }
+
+        let mut queue = VecDeque::new();
+
+        visited[root] = true;
+        queue.push_back(root);
+
+        while let Some(x) = queue.pop_front() {
+            labels[x] = Label::Cluster(clusters);
+
+            // Border points join the cluster but do not extend it.
+            if neighbours[x].len() < min_points {
+                continue;
+            }
+
+            for &y in &neighbours[x] {
+                if !visited[y] {
+                    visited[y] = true;
+                    queue.push_back(y);
+                }
+            }
+        }
+
+        clusters += 1;
+    }
+
+    Density{labels: labels, clusters: clusters}
+}
+
+// A cluster of the condensed tree. Lambda is 1 / distance, so it grows going down the tree.
+#[derive(Debug)]
+struct Condensed {
+    birth: f64,
+    stability: f64,
+    children: Vec<usize>,
+    points: Vec<usize>,
+}
+
+// Every leaf below a dendrogram node.
+fn leaves(d: &Dendrogram, node: usize) -> Vec<usize> {
+    let mut found = vec![];
+    let mut stack = vec![node];
+
+    while let Some(x) = stack.pop() {
+        if x < d.leaves() {
+            found.push(x);
+        } else {
+            let ref m = d.merges()[x - d.leaves()];
+
+            stack.push(m.left);
+            stack.push(m.right);
+        }
+    }
+
+    found
+}
+
+fn size(d: &Dendrogram, node: usize) -> usize {
+    if node < d.leaves() { 1 } else { d.merges()[node - d.leaves()].size }
+}
+
+// HDBSCAN. The core distance of a point is the distance to its min_samples-th nearest
+// neighbour, itself included. Single linkage under mutual reachability gives a dendrogram,
+// which is condensed by treating any split that leaves fewer than min_cluster_size points
+// as those points falling out as noise. The most stable clusters of the condensed tree are
+// kept, never the root: a point belongs to the kept cluster it fell out of, if any.
+pub fn hdbscan(c: &Coordinates, min_cluster_size: usize, min_samples: usize) -> Result<Density, &'static str> {
+    if min_cluster_size < 2 || min_samples == 0 {
+        return Err("Invalid parameters");
+    }
+
+    let n = c.len();
+    let tree = KdTree::new(c);
+    let core: Vec<f64> = (0..n)
+        .map(|x| tree.nearest(c, c.point(x), min_samples).last().map_or(0.0, |b| b.0))
+        .collect();
+    let d = Dendrogram::from_edges(n, reachability_mst(c, &core));
+    let mut condensed = vec![Condensed{birth: 0.0, stability: 0.0, children: vec![], points: vec![]}];
+
+    if n > 1 {
+        let mut stack = vec![(n + d.merges().len() - 1, 0)];
+
+        while let Some((node, id)) = stack.pop() {
+            if node < n {
+                condensed[id].points.push(node);
+                continue;
+            }
+
+            let ref m = d.merges()[node - n];
+            let lambda = 1.0 / m.height;
+            let (big_left, big_right) = (size(&d, m.left) >= min_cluster_size, size(&d, m.right) >= min_cluster_size);
+            let birth = condensed[id].birth;
+
+            if big_left && big_right {
+                condensed[id].stability += m.size as f64 * (lambda - birth);
+
+                for &child in &[m.left, m.right] {
+                    let next = condensed.len();
+
+                    condensed.push(Condensed{birth: lambda, stability: 0.0, children: vec![], points: vec![]});
+                    condensed[id].children.push(next);
+                    stack.push((child, next));
+                }
+
+                continue;
+            }
+
+            for &(child, big) in &[(m.left, big_left), (m.right, big_right)] {
+                if big {
+                    stack.push((child, id));
+                } else {
+                    let fallen = leaves(&d, child);
+
+                    condensed[id].stability += fallen.len() as f64 * (lambda - birth);
+                    condensed[id].points.extend(fallen);
+                }
+            }
+        }
+    }
+
+    // Children always come after their parent, so a backwards sweep settles them first.
+    let mut selected = vec![false; condensed.len()];
+    let mut score = vec![0.0; condensed.len()];
+
+    for id in (0..condensed.len()).rev() {
+        let below: f64 = condensed[id].children.iter().map(|&k| score[k]).sum();
+
+        if id > 0 && (condensed[id].children.is_empty() || condensed[id].stability >= below) {
+            selected[id] = true;
+            score[id] = condensed[id].stability;
+
+            let mut stack = condensed[id].children.clone();
+
+            while let Some(k) = stack.pop() {
+                selected[k] = false;
+                stack.extend(condensed[k].children.iter().cloned());
+            }
+        } else {
+            score[id] = below;
+        }
+    }
+
+    let mut labels = vec![Label::Noise; n];
+    let mut clusters = 0;
+
+    for id in 0..condensed.len() {
+        if !selected[id] {
+            continue;
+        }
+
+        let mut stack = vec![id];
+
+        while let Some(k) = stack.pop() {
+            for &x in &condensed[k].points {
+                labels[x] = Label::Cluster(clusters);
+            }
+
+            stack.extend(condensed[k].children.iter().cloned());
+        }
+
+        clusters += 1;
+    }
+
+    Ok(Density{labels: labels, clusters: clusters})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use points::Coordinates;
+    use rng::Rng;
+
+    // Two dense discs and a few scattered outliers.
+    fn sample() -> Coordinates {
+        let mut rng = Rng::new(12);
+        let mut values = vec![];
+
+        for &(x, y) in &[(0.0, 0.0), (20.0, 0.0)] {
+            for _ in 0..60 {
+                let (r, a) = (rng.next_f64() * 2.0, rng.next_f64() * 6.283);
+
+                values.push(x + r * a.cos());
+                values.push(y + r * a.sin());
+            }
+        }
+
+        for &(x, y) in &[(10.0, 30.0), (-30.0, -25.0), (40.0, 40.0)] {
+            values.push(x);
+            values.push(y);
+        }
+
+        Coordinates::new(2, values)
+    }
+
+    #[test]
+    fn dbscan_sample() {
+        let c = sample();
+        let result = dbscan(&c, 1.5, 4);
+
+        assert_eq!(result.clusters, 2);
+        assert_eq!(result.noise(), vec![120, 121, 122]);
+        assert!(result.members()[0].iter().all(|&x| x < 60));
+        assert!(result.members()[1].iter().all(|&x| x >= 60 && x < 120));
+    }
+
+    #[test]
+    fn dbscan_chain() {
+        // A border point reached from two clusters stays with the first.
+        let c = Coordinates::new(1, vec![0.0, 0.5, 1.0, 2.0, 3.0, 3.5, 4.0]);
+        let result = dbscan(&c, 1.0, 4);
+
+        assert_eq!(result.clusters, 2);
+        assert_eq!(result.labels[3], Label::Cluster(0));
+        assert_eq!(result.labels[6], Label::Cluster(1));
+    }
+
+    #[test]
+    fn hdbscan_sample() {
+        let c = sample();
+        let result = hdbscan(&c, 10, 5).ok().unwrap();
+        let members = result.members();
+
+        assert_eq!(result.clusters, 2);
+        assert!(members[0].iter().all(|&x| x < 60) || members[0].iter().all(|&x| x >= 60 && x < 120));
+        assert!(members.iter().all(|m| m.len() >= 50));
+
+        for x in 120..123 {
+            assert_eq!(result.labels[x], Label::Noise);
+        }
+
+        assert!(hdbscan(&c, 1, 5).is_err());
+    }
+
+    #[test]
+    fn hdbscan_small() {
+        // Too few points to split, and the root is never a cluster on its own.
+        let c = Coordinates::new(1, vec![0.0, 1.0, 2.0, 5.0]);
+        let result = hdbscan(&c, 5, 2).ok().unwrap();
+
+        assert_eq!(result.clusters, 0);
+        assert_eq!(result.noise().len(), 4);
+    }
+}
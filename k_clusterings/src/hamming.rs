@@ -0,0 +1,269 @@
+use std::path::Path;
+use std::io::BufReader;
+use std::io::BufRead;
+use std::io::{Error, ErrorKind};
+use std::fs::File;
+use std::collections::HashMap;
+
+use Clustering;
+use union_find::UnionFind;
+
+// Points given as bit vectors, each packed into an integer with the first bit highest.
+// Distance between two points is the number of bits they differ in.
+#[derive(Debug, Clone)]
+pub struct Points {
+    pub bits: usize,
+    pub points: Vec<u64>,
+}
+
+impl Points {
+    pub fn new(bits: usize, points: Vec<u64>) -> Points {
+        if bits > 63 {
+            panic!("Too many bits: {:?}", bits);
+        }
+
+        Points{bits: bits, points: points}
+    }
+
+    // Reads the course format: "n bits", then one line of n space separated 0s and 1s per
+    // point.
+    pub fn from_file(path: &Path) -> Result<Points, Error> {
+        let file = try!(File::open(path));
+        let mut buffer = BufReader::new(&file);
+        let mut first_line = String::new();
+
+        try!(buffer.read_line(&mut first_line));
+
+        let mut details: Vec<usize> = vec![];
+
+        for d in first_line.split_whitespace() {
+            match d.parse() {
+                Ok(v) => details.push(v),
+                Err(_) => return Err(Error::new(ErrorKind::InvalidData, "Invalid header")),
+            }
+        }
+
+        if details.len() != 2 || details[1] > 63 {
+            return Err(Error::new(ErrorKind::InvalidData, "Invalid header"));
+        }
+
+        // The header is only trusted as a hint until the points are counted.
+        let mut points = Vec::with_capacity(std::cmp::min(details[0], 1 << 20));
+
+        for l in buffer.lines() {
+            let line = try!(l);
+            let mut value = 0;
+            let mut count = 0;
+
+            for d in line.split_whitespace() {
+                value = match d {
+                    "0" => value << 1,
+                    "1" => (value << 1) | 1,
+                    _ => return Err(Error::new(ErrorKind::InvalidData, "Invalid bit")),
+                };
+                count += 1;
+            }
+
+            if count == 0 {
+                continue;
+            }
+
+            if count != details[1] {
+                return Err(Error::new(ErrorKind::InvalidData, "Wrong number of bits"));
+            }
+
+            points.push(value);
+        }
+
+        if points.len() != details[0] {
+            return Err(Error::new(ErrorKind::InvalidData, "Wrong number of points"));
+        }
+
+        Ok(Points::new(details[1], points))
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn distance(&self, a: usize, b: usize) -> u32 {
+        (self.points[a] ^ self.points[b]).count_ones()
+    }
+}
+
+// Every mask of the given width with exactly count bits set, by Gosper's hack.
+fn flips(bits: usize, count: u32) -> Vec<u64> {
+    let mut masks = vec![];
+
+    if count as usize > bits {
+        return masks;
+    }
+
+    if count == 0 {
+        return vec![0];
+    }
+
+    let limit = 1u64 << bits;
+    let mut mask = (1u64 << count) - 1;
+
+    while mask < limit {
+        masks.push(mask);
+
+        let low = mask & mask.wrapping_neg();
+        let ripple = mask + low;
+
+        mask = (((ripple ^ mask) >> 2) / low) | ripple;
+    }
+
+    masks
+}
+
+// Merges every pair of points closer than spacing, so the clusters left are exactly the most
+// there can be while keeping them at least spacing apart. Neighbours are found by flipping
+// up to spacing - 1 bits of each point and looking the result up, rather than comparing all
+// pairs. The clustering's spacing is the threshold, a lower bound on the real gap.
+pub fn hamming_clusters(p: &Points, spacing: u32) -> Clustering {
+    let mut clusters = UnionFind::new(p.len());
+    let mut first: HashMap<u64, usize> = HashMap::new();
+    let mut merged = vec![];
+
+    // Identical points are distance 0 apart and always merged.
+    for (i, &x) in p.points.iter().enumerate() {
+        let f = *first.entry(x).or_insert(i);
+
+        if f != i && spacing > 0 && clusters.union(f, i) {
+            merged.push((f, i, 0));
+        }
+    }
+
+    for d in 1..spacing {
+        for mask in flips(p.bits, d) {
+            for (&x, &i) in &first {
+                if let Some(&j) = first.get(&(x ^ mask)) {
+                    if clusters.union(i, j) {
+                        merged.push((i, j, d as i32));
+                    }
+                }
+            }
+        }
+    }
+
+    Clustering::new(&mut clusters, spacing as i32, merged)
+}
+
+// The largest number of clusters with spacing at least the threshold.
+pub fn max_clusters(p: &Points, spacing: u32) -> usize {
+    hamming_clusters(p, spacing).clusters()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use rng::Rng;
+
+    #[test]
+    fn representation() {
+        let p = Points::from_file(Path::new("hamming_small1.txt")).ok().unwrap();
+
+        assert_eq!(p.len(), 10);
+        assert_eq!(p.bits, 8);
+        assert_eq!(p.points[0], 0b11110000);
+        assert_eq!(p.distance(0, 1), 1);
+    }
+
+    #[test]
+    fn masks() {
+        assert_eq!(flips(4, 0), vec![0]);
+        assert_eq!(flips(4, 2), vec![0b0011, 0b0101, 0b0110, 0b1001, 0b1010, 0b1100]);
+        assert_eq!(flips(24, 2).len(), 276);
+        assert!(flips(3, 4).is_empty());
+    }
+
+    #[test]
+    fn simple1() {
+        let p = Points::from_file(Path::new("hamming_small1.txt")).ok().unwrap();
+
+        assert_eq!(max_clusters(&p, 1), 9);
+        assert_eq!(max_clusters(&p, 3), 5);
+
+        let c = hamming_clusters(&p, 3);
+
+        assert_eq!(c.cluster[0], c.cluster[3]);
+        assert_eq!(c.cluster[8], c.cluster[9]);
+        assert!(c.cluster[0] != c.cluster[4]);
+        assert_eq!(c.merged.len(), p.len() - c.clusters());
+    }
+
+    #[test]
+    fn brute_force() {
+        let mut rng = Rng::new(7);
+
+        for spacing in 1..5 {
+            let points: Vec<u64> = (0..60).map(|_| rng.below(1024) as u64).collect();
+            let p = Points::new(10, points);
+            let mut clusters = UnionFind::new(p.len());
+
+            for a in 0..p.len() {
+                for b in (a + 1)..p.len() {
+                    if p.distance(a, b) < spacing {
+                        clusters.union(a, b);
+                    }
+                }
+            }
+
+            assert_eq!(max_clusters(&p, spacing), clusters.components());
+        }
+    }
+
+    #[test]
+    fn wrong_count() {
+        let p = std::env::temp_dir().join(format!("k_clusterings-{}-wrong-count.txt", std::process::id()));
+
+        for contents in &["3 2\n0 1\n1 1\n", "1 2\n0 1\n1 1\n", "x 2\n0 1\n"] {
+            std::fs::write(&p, contents).ok().unwrap();
+            assert_eq!(Points::from_file(&p).err().unwrap().kind(), ErrorKind::InvalidData);
+        }
+
+        std::fs::remove_file(&p).ok().unwrap();
+    }
+
+    #[test]
+    fn realistic() {
+        // 24 bits as in the course data: 200 centres with 15 points each at most 2 bits away.
+        let mut rng = Rng::new(24);
+        let mut points = vec![];
+
+        for _ in 0..200 {
+            let centre = rng.below(1 << 24) as u64;
+
+            for _ in 0..15 {
+                points.push(centre ^ (1 << rng.below(24)) ^ (1 << rng.below(24)));
+            }
+        }
+
+        let p = Points::new(24, points);
+
+        for spacing in 3..5 {
+            let mut clusters = UnionFind::new(p.len());
+
+            for a in 0..p.len() {
+                for b in (a + 1)..p.len() {
+                    if p.distance(a, b) < spacing {
+                        clusters.union(a, b);
+                    }
+                }
+            }
+
+            let c = hamming_clusters(&p, spacing);
+
+            assert_eq!(c.clusters(), clusters.components());
+            assert_eq!(c.merged.len(), p.len() - c.clusters());
+            assert!(c.clusters() < p.len() / 2);
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use points::Coordinates;
+use rng::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    // Every point measured against every centroid, every iteration.
+    Lloyd,
+    // The same iterations, skipping distances that the triangle inequality rules out.
+    Elkan,
+}
+
+#[derive(Debug, Clone)]
+pub struct KMeans {
+    pub centroids: Vec<Vec<f64>>,
+    pub assignment: Vec<usize>,
+    pub inertia: f64,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+impl KMeans {
+    pub fn members(&self) -> Vec<Vec<usize>> {
+        let mut members = vec![vec![]; self.centroids.len()];
+
+        for (x, &j) in self.assignment.iter().enumerate() {
+            members[j].push(x);
+        }
+
+        members
+    }
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum::<f64>().sqrt()
+}
+
+// k-means++: each further seed is a point picked with probability proportional to its
+// squared distance from the nearest seed so far.
+pub fn seed_plus_plus(c: &Coordinates, k: usize, rng: &mut Rng) -> Vec<usize> {
+    let n = c.len();
+    let mut seeds = vec![rng.below(n)];
+    let mut nearest: Vec<f64> = (0..n).map(|x| distance(c.point(x), c.point(seeds[0])).powi(2)).collect();
+
+    while seeds.len() < k {
+        let total: f64 = nearest.iter().sum();
+        let mut next = n - 1;
+
+        if total > 0.0 {
+            let mut target = rng.next_f64() * total;
+
+            for (x, &d) in nearest.iter().enumerate() {
+                if target < d {
+                    next = x;
+                    break;
+                }
+
+                target -= d;
+            }
+        } else {
+            // Every point sits on a seed already.
+            next = (0..n).find(|x| !seeds.contains(x)).unwrap();
+        }
+
+        seeds.push(next);
+
+        for x in 0..n {
+            let d = distance(c.point(x), c.point(next)).powi(2);
+
+            if d < nearest[x] {
+                nearest[x] = d;
+            }
+        }
+    }
+
+    seeds
+}
+
+// Moves each centroid to the mean of its points and returns how far each one moved. A
+// centroid left without points takes over the point farthest from its own centroid, so no
+// cluster is ever empty.
+fn update(c: &Coordinates, assignment: &mut [usize], centroids: &mut Vec<Vec<f64>>) -> Vec<f64> {
+    let k = centroids.len();
+    let mut sums = vec![vec![0.0; c.dimensions()]; k];
+    let mut counts = vec![0; k];
+
+    for (x, &j) in assignment.iter().enumerate() {
+        counts[j] += 1;
+
+        for (s, v) in sums[j].iter_mut().zip(c.point(x)) {
+            *s += *v;
+        }
+    }
+
+    let mut updated: Vec<Vec<f64>> = sums.iter().zip(&counts)
+        .enumerate()
+        .map(|(j, (s, &count))| {
+            if count == 0 { centroids[j].clone() } else { s.iter().map(|v| v / count as f64).collect() }
+        })
+        .collect();
+
+    for j in 0..k {
+        if counts[j] > 0 {
+            continue;
+        }
+
+        let far = (0..c.len())
+            .filter(|&x| counts[assignment[x]] > 1)
+            .max_by(|&a, &b| {
+                let da = distance(c.point(a), &updated[assignment[a]]);
+                let db = distance(c.point(b), &updated[assignment[b]]);
+
+                da.partial_cmp(&db).unwrap()
+            })
+            .unwrap();
+
+        counts[assignment[far]] -= 1;
+        counts[j] = 1;
+        assignment[far] = j;
+        updated[j] = c.point(far).to_vec();
+    }
+
+    let shifts = centroids.iter().zip(&updated).map(|(a, b)| distance(a, b)).collect();
+
+    *centroids = updated;
+
+    shifts
+}
+
+// Lloyd's assignment step. Points only move to a strictly closer centroid, the lowest
+// numbered on ties, which Elkan's bounds reproduce exactly.
+fn assign_lloyd(c: &Coordinates, assignment: &mut [usize], centroids: &[Vec<f64>]) -> bool {
+    let mut changed = false;
+
+    for x in 0..c.len() {
+        let mut best = assignment[x];
+        let mut d = distance(c.point(x), &centroids[best]);
+
+        for (j, centroid) in centroids.iter().enumerate() {
+            let dj = distance(c.point(x), centroid);
+
+            if dj < d {
+                best = j;
+                d = dj;
+            }
+        }
+
+        if best != assignment[x] {
+            assignment[x] = best;
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+// Elkan's bounds: an upper bound on each point's distance to its own centroid and a lower
+// bound on its distance to every other.
+struct Bounds {
+    upper: Vec<f64>,
+    lower: Vec<Vec<f64>>,
+}
+
+impl Bounds {
+    fn new(c: &Coordinates, assignment: &[usize], centroids: &[Vec<f64>]) -> Bounds {
+        let lower: Vec<Vec<f64>> = (0..c.len())
+            .map(|x| centroids.iter().map(|centroid| distance(c.point(x), centroid)).collect())
+            .collect();
+        let upper = (0..c.len()).map(|x| lower[x][assignment[x]]).collect();
+
+        Bounds{upper: upper, lower: lower}
+    }
+
+    fn shift(&mut self, assignment: &[usize], shifts: &[f64]) {
+        for x in 0..self.upper.len() {
+            self.upper[x] += shifts[assignment[x]];
+
+            for (l, s) in self.lower[x].iter_mut().zip(shifts) {
+                *l = if *l > *s { *l - *s } else { 0.0 };
+            }
+        }
+    }
+}
+
+fn assign_elkan(c: &Coordinates, assignment: &mut [usize], centroids: &[Vec<f64>], b: &mut Bounds) -> bool {
+    let k = centroids.len();
+    let mut changed = false;
+
+    // Half the distance between each pair of centroids, and from each to its nearest.
+    let half: Vec<Vec<f64>> = centroids.iter()
+        .map(|a| centroids.iter().map(|z| distance(a, z) / 2.0).collect())
+        .collect();
+    let nearest: Vec<f64> = (0..k)
+        .map(|j| (0..k).filter(|&i| i != j).map(|i| half[j][i]).fold(std::f64::INFINITY, f64::min))
+        .collect();
+
+    for x in 0..c.len() {
+        let mut own = assignment[x];
+
+        if b.upper[x] <= nearest[own] {
+            continue;
+        }
+
+        let mut tight = false;
+
+        for j in 0..k {
+            if j == own || b.upper[x] <= b.lower[x][j] || b.upper[x] <= half[own][j] {
+                continue;
+            }
+
+            if !tight {
+                b.upper[x] = distance(c.point(x), &centroids[own]);
+                b.lower[x][own] = b.upper[x];
+                tight = true;
+
+                if b.upper[x] <= b.lower[x][j] || b.upper[x] <= half[own][j] {
+                    continue;
+                }
+            }
+
+            let dj = distance(c.point(x), &centroids[j]);
+
+            b.lower[x][j] = dj;
+
+            if dj < b.upper[x] {
+                own = j;
+                b.upper[x] = dj;
+            }
+        }
+
+        if own != assignment[x] {
+            assignment[x] = own;
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+// k-means from k-means++ seeds, until no point changes cluster or max_iterations updates
+// have run. Both variants give the same result for the same seed.
+pub fn kmeans(c: &Coordinates, k: usize, variant: Variant, max_iterations: usize, rng: &mut Rng) -> Result<KMeans, &'static str> {
+    if k == 0 || k > c.len() {
+        return Err("Invalid k");
+    }
+
+    let mut centroids: Vec<Vec<f64>> = seed_plus_plus(c, k, rng).iter().map(|&x| c.point(x).to_vec()).collect();
+    let mut assignment = vec![0; c.len()];
+    let mut iterations = 0;
+    let mut converged = false;
+
+    assign_lloyd(c, &mut assignment, &centroids);
+
+    let mut bounds = if variant == Variant::Elkan { Some(Bounds::new(c, &assignment, &centroids)) } else { None };
+
+    while iterations < max_iterations {
+        let before = assignment.clone();
+        let shifts = update(c, &mut assignment, &mut centroids);
+
+        iterations += 1;
+
+        let changed = match bounds {
+            Some(ref mut b) => {
+                // A relocated point sits exactly on its new centroid.
+                for x in 0..c.len() {
+                    if assignment[x] != before[x] {
+                        b.upper[x] = 0.0;
+                    }
+                }
+
+                b.shift(&assignment, &shifts);
+                assign_elkan(c, &mut assignment, &centroids, b)
+            },
+            None => assign_lloyd(c, &mut assignment, &centroids),
+        };
+
+        if !changed && assignment == before {
+            converged = true;
+            break;
+        }
+    }
+
+    let inertia = (0..c.len()).map(|x| distance(c.point(x), &centroids[assignment[x]]).powi(2)).sum();
+
+    Ok(KMeans{centroids: centroids, assignment: assignment, inertia: inertia, iterations: iterations, converged: converged})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use points::Coordinates;
+    use rng::Rng;
+
+    // Gaussian-ish blobs around the given centres.
+    fn blobs(centres: &[(f64, f64)], each: usize, rng: &mut Rng) -> Coordinates {
+        let mut values = vec![];
+
+        for &(x, y) in centres {
+            for _ in 0..each {
+                let (dx, dy): (f64, f64) = ((0..4).map(|_| rng.next_f64() - 0.5).sum(), (0..4).map(|_| rng.next_f64() - 0.5).sum());
+
+                values.push(x + dx);
+                values.push(y + dy);
+            }
+        }
+
+        Coordinates::new(2, values)
+    }
+
+    #[test]
+    fn simple1() {
+        let c = Coordinates::from_file(Path::new("points_simple1.txt")).ok().unwrap();
+        let result = kmeans(&c, 3, Variant::Lloyd, 100, &mut Rng::new(1)).ok().unwrap();
+        let mut members = result.members();
+
+        members.sort();
+
+        assert!(result.converged);
+        assert_eq!(members, vec![vec![0, 1, 2], vec![3, 4, 5], vec![6, 7]]);
+        assert!(kmeans(&c, 9, Variant::Lloyd, 100, &mut Rng::new(1)).is_err());
+    }
+
+    #[test]
+    fn elkan_matches_lloyd() {
+        let c = blobs(&[(0.0, 0.0), (10.0, 0.0), (5.0, 8.0), (5.0, 3.0)], 100, &mut Rng::new(3));
+
+        for seed in 0..5 {
+            let lloyd = kmeans(&c, 4, Variant::Lloyd, 100, &mut Rng::new(seed)).ok().unwrap();
+            let elkan = kmeans(&c, 4, Variant::Elkan, 100, &mut Rng::new(seed)).ok().unwrap();
+
+            assert_eq!(lloyd.assignment, elkan.assignment);
+            assert_eq!(lloyd.iterations, elkan.iterations);
+            assert!((lloyd.inertia - elkan.inertia).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn reproducible() {
+        let c = blobs(&[(0.0, 0.0), (6.0, 6.0)], 50, &mut Rng::new(5));
+        let a = kmeans(&c, 5, Variant::Elkan, 100, &mut Rng::new(9)).ok().unwrap();
+        let b = kmeans(&c, 5, Variant::Elkan, 100, &mut Rng::new(9)).ok().unwrap();
+
+        assert_eq!(a.assignment, b.assignment);
+        assert_eq!(a.centroids, b.centroids);
+    }
+
+    #[test]
+    fn duplicates() {
+        // Fewer distinct points than clusters still leaves every cluster a point.
+        let c = Coordinates::new(1, vec![1.0, 1.0, 1.0, 2.0]);
+        let result = kmeans(&c, 3, Variant::Lloyd, 100, &mut Rng::new(0)).ok().unwrap();
+
+        assert!(result.members().iter().all(|m| !m.is_empty()));
+        assert_eq!(result.inertia, 0.0);
+    }
+}
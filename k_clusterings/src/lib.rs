@@ -4,6 +4,22 @@ use std::io::BufRead;
 use std::fs::File;
 use std::cmp::Ordering;
 
+pub mod constraints;
+pub mod dendrogram;
+pub mod density;
+pub mod hamming;
+pub mod kmeans;
+pub mod linkage;
+pub mod medoids;
+pub mod metrics;
+pub mod points;
+pub mod rng;
+pub mod select;
+pub mod stream;
+pub mod union_find;
+
+use union_find::UnionFind;
+
 #[derive(Debug)]
 pub struct Graph {
     vertices: Vec<Vertex>,
@@ -45,7 +61,7 @@ impl PartialEq for Edge {
 impl Eq for Edge { }
 
 impl Graph {
-    fn new() -> Graph {
+    pub fn new() -> Graph {
         Graph{vertices: vec![]}
     }
 
@@ -67,7 +83,7 @@ impl Graph {
             .collect()
     }
 
-    fn from_file(path: &Path) -> Result<Graph, std::io::Error> {
+    pub fn from_file(path: &Path) -> Result<Graph, std::io::Error> {
         let mut g = Graph{vertices: vec![]};
         let file = try!(File::open(path));
         let mut buffer = BufReader::new(&file);
@@ -97,26 +113,46 @@ impl Graph {
     }
 }
 
-// Inefficient O(n) implementation. Recursive implementation proved troublesome.
-fn update_leaders(g: &mut Graph, start: usize, leader: usize) {
-    for mut v in &mut g.vertices {
-        if v.leader == start {
-            v.leader = leader;
+// The outcome of single-link clustering. Clusters are numbered 0..k in order of their
+// lowest vertex, and merged holds the MST edges (head, tail, cost) joined along the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clustering {
+    pub cluster: Vec<usize>,
+    pub members: Vec<Vec<usize>>,
+    pub spacing: i32,
+    pub merged: Vec<(usize, usize, i32)>,
+}
+
+impl Clustering {
+    fn new(clusters: &mut UnionFind, spacing: i32, merged: Vec<(usize, usize, i32)>) -> Clustering {
+        let leaders = clusters.leaders();
+        let mut id = vec![std::usize::MAX; leaders.len()];
+        let mut cluster = Vec::with_capacity(leaders.len());
+        let mut members: Vec<Vec<usize>> = vec![];
+
+        for (v, &l) in leaders.iter().enumerate() {
+            if id[l] == std::usize::MAX {
+                id[l] = members.len();
+                members.push(vec![]);
+            }
+
+            cluster.push(id[l]);
+            members[id[l]].push(v);
         }
+
+        Clustering{cluster: cluster, members: members, spacing: spacing, merged: merged}
+    }
+
+    pub fn clusters(&self) -> usize {
+        self.members.len()
     }
 }
 
-pub fn cluster_spacing(groups: usize, g: &mut Graph) -> i32 {
-    let mut t = Graph::new();
+// Every edge of the graph, cheapest first.
+fn sorted_edges(g: &Graph) -> Vec<Edge> {
     let mut edges: Vec<Edge> = vec![];
-    let mut clusters = g.vertices.len();
-    let mut sizes: Vec<i32> = vec![];
-
-    t.build(clusters as i32);
 
     for v in &g.vertices {
-        sizes.push(1);
-
         for e in &v.edges {
             edges.push(Edge{head: e.head, tail: e.tail, cost: e.cost});
         }
@@ -124,33 +160,59 @@ pub fn cluster_spacing(groups: usize, g: &mut Graph) -> i32 {
 
     edges.sort_by(|a, b| a.cmp(b));
 
-    for e in &edges {
-        let hleader = t.vertices[e.head].leader;
-        let tleader = t.vertices[e.tail].leader;
+    edges
+}
 
-        if hleader != tleader {
-            if clusters <= groups {
-                return e.cost;
+// Kruskal's algorithm stopped at the given number of groups. The spacing is the cost of the
+// cheapest edge left between two clusters, or 0 if the graph never got down to that many.
+// Each vertex's leader is updated to the id of its cluster.
+pub fn cluster_spacing(groups: usize, g: &mut Graph) -> Clustering {
+    let mut clusters = UnionFind::new(g.vertices.len());
+    let mut merged = vec![];
+    let mut spacing = 0;
+
+    for e in &sorted_edges(g) {
+        if !clusters.connected(e.head, e.tail) {
+            if clusters.components() <= groups {
+                spacing = e.cost;
+                break;
             }
 
-            if sizes[hleader] >= sizes[tleader] {
-                update_leaders(&mut t, tleader, hleader);
-                sizes[hleader] += sizes[tleader];
-                sizes[tleader] = 0;
-            } else {
-                update_leaders(&mut t, hleader, tleader);
-                sizes[tleader] += sizes[hleader];
-                sizes[hleader] = 0;
-            }
+            clusters.union(e.head, e.tail);
+            merged.push((e.head, e.tail, e.cost));
+        }
+    }
 
-            t.vertices[e.head].edges.push(Edge{head: e.head, tail: e.tail, cost: e.cost});
-            t.vertices[e.tail].edges.push(Edge{head: e.tail, tail: e.head, cost: e.cost});
+    let result = Clustering::new(&mut clusters, spacing, merged);
 
-            clusters -= 1;
-        }
+    for v in &mut g.vertices {
+        v.leader = result.cluster[v.label as usize];
     }
 
-    0
+    result
+}
+
+// Graphs shared by the tests of several modules.
+#[cfg(test)]
+mod fixtures {
+    use {Graph, Edge};
+
+    // Points on a line, every pair joined by an edge costing their distance.
+    pub fn line(points: &[i32]) -> Graph {
+        let mut g = Graph::new();
+
+        g.build(points.len() as i32);
+
+        for a in 0..points.len() {
+            for b in 0..points.len() {
+                if a != b {
+                    g.vertices[a].edges.push(Edge{head: a, tail: b, cost: (points[a] - points[b]).abs()});
+                }
+            }
+        }
+
+        g
+    }
 }
 
 #[cfg(test)]
@@ -201,7 +263,7 @@ mod tests {
         let p = Path::new("clustering1.txt");
         let mut g = Graph::from_file(p).ok().unwrap();
 
-        assert_eq!(cluster_spacing(4, &mut g), 106)
+        assert_eq!(cluster_spacing(4, &mut g).spacing, 106)
     }
 
     #[test]
@@ -209,7 +271,7 @@ mod tests {
         let p = Path::new("clustering_small1.txt");
         let mut g = Graph::from_file(p).ok().unwrap();
 
-        assert_eq!(cluster_spacing(4, &mut g), 2)
+        assert_eq!(cluster_spacing(4, &mut g).spacing, 2)
     }
 
     #[test]
@@ -217,7 +279,7 @@ mod tests {
         let p = Path::new("clustering_small2.txt");
         let mut g = Graph::from_file(p).ok().unwrap();
 
-        assert_eq!(cluster_spacing(4, &mut g), 8)
+        assert_eq!(cluster_spacing(4, &mut g).spacing, 8)
     }
 
     #[test]
@@ -225,7 +287,7 @@ mod tests {
         let p = Path::new("clustering_small3.txt");
         let mut g = Graph::from_file(p).ok().unwrap();
 
-        assert_eq!(cluster_spacing(4, &mut g), 7)
+        assert_eq!(cluster_spacing(4, &mut g).spacing, 7)
     }
 
     #[test]
@@ -233,7 +295,32 @@ mod tests {
         let p = Path::new("clustering_small4.txt");
         let mut g = Graph::from_file(p).ok().unwrap();
 
-        assert_eq!(cluster_spacing(4, &mut g), 17)
+        assert_eq!(cluster_spacing(4, &mut g).spacing, 17)
     }
 
+    #[test]
+    fn assignments() {
+        let p = Path::new("clustering_small1.txt");
+        let mut g = Graph::from_file(p).ok().unwrap();
+        let c = cluster_spacing(4, &mut g);
+        let n = g.vertices.len();
+
+        assert_eq!(c.clusters(), 4);
+        assert_eq!(c.merged.len(), n - 4);
+        assert_eq!(c.members.iter().map(|m| m.len()).sum::<usize>(), n);
+        assert_eq!(c.cluster[0], 0);
+
+        for (id, m) in c.members.iter().enumerate() {
+            for &v in m {
+                assert_eq!(c.cluster[v], id);
+                assert_eq!(g.vertices[v].leader, id);
+            }
+        }
+
+        // Every merged edge was cheaper than the spacing and stays inside one cluster.
+        for &(h, t, cost) in &c.merged {
+            assert!(cost <= c.spacing);
+            assert_eq!(c.cluster[h], c.cluster[t]);
+        }
+    }
 }
@@ -0,0 +1,295 @@
+use Graph;
+use dendrogram::{Cut, Dendrogram};
+
+// How the distance between two clusters follows from the distances between their vertices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Linkage {
+    // The closest pair.
+    Single,
+    // The farthest pair.
+    Complete,
+    // The mean over all pairs (UPGMA).
+    Average,
+    // The increase in total within-cluster variance, treating edge costs as Euclidean
+    // distances.
+    Ward,
+}
+
+// The most vertices agglomerate takes for anything but single linkage. The distance matrix
+// for this many is about 1 GB.
+pub const MAX_VERTICES: usize = 16000;
+
+// Pairwise distances between clusters, kept as the upper triangle of the matrix. Vertex
+// pairs without an edge are infinitely far apart.
+struct Distances {
+    size: usize,
+    d: Vec<f64>,
+}
+
+impl Distances {
+    fn new(g: &Graph, squared: bool) -> Distances {
+        let size = g.vertices.len();
+        let mut distances = Distances{size: size, d: vec![std::f64::INFINITY; size * size.saturating_sub(1) / 2]};
+
+        for v in &g.vertices {
+            for e in &v.edges {
+                if e.head == e.tail {
+                    continue;
+                }
+
+                let cost = if squared { e.cost as f64 * e.cost as f64 } else { e.cost as f64 };
+
+                // Parallel edges keep the cheapest.
+                if cost < distances.get(e.head, e.tail) {
+                    distances.set(e.head, e.tail, cost);
+                }
+            }
+        }
+
+        distances
+    }
+
+    fn index(&self, a: usize, b: usize) -> usize {
+        let (i, j) = if a < b { (a, b) } else { (b, a) };
+
+        self.size * i - i * (i + 1) / 2 + j - i - 1
+    }
+
+    fn get(&self, a: usize, b: usize) -> f64 {
+        self.d[self.index(a, b)]
+    }
+
+    fn set(&mut self, a: usize, b: usize, value: f64) {
+        let i = self.index(a, b);
+
+        self.d[i] = value;
+    }
+}
+
+// Agglomerative clustering by the nearest-neighbour chain: follow nearest neighbours until
+// two clusters are each other's nearest, merge them and carry on from the rest of the chain.
+// Each merge updates the distances by Lance-Williams, so the whole run is O(n^2) time. The
+// matrix takes O(n^2) memory, 4 * n^2 bytes (400 MB at 10,000 vertices), so graphs with
+// more than MAX_VERTICES are rejected. Single linkage goes through Kruskal's instead and
+// has no limit. Clusters with nothing but missing edges between them are never merged.
+pub fn agglomerate(g: &Graph, linkage: Linkage) -> Result<Dendrogram, &'static str> {
+    if linkage == Linkage::Single {
+        return Ok(Dendrogram::single_linkage(g));
+    }
+
+    let n = g.vertices.len();
+
+    if n > MAX_VERTICES {
+        return Err("Too many vertices for the distance matrix");
+    }
+
+    let ward = linkage == Linkage::Ward;
+    let mut d = Distances::new(g, ward);
+    let mut size = vec![1; n];
+    let mut active = vec![true; n];
+    let mut remaining = n;
+    let mut chain: Vec<usize> = vec![];
+    let mut found: Vec<(usize, usize, f64)> = vec![];
+
+    // A cluster lives in the slot of one of its vertices.
+    while remaining > 1 {
+        if chain.is_empty() {
+            chain.push((0..n).find(|&x| active[x]).unwrap());
+        }
+
+        let a = chain[chain.len() - 1];
+        let previous = if chain.len() > 1 { Some(chain[chain.len() - 2]) } else { None };
+
+        // Preferring the previous link on ties keeps the chain from going round in circles.
+        let mut best = previous;
+        let mut distance = previous.map_or(std::f64::INFINITY, |p| d.get(a, p));
+
+        for x in 0..n {
+            if active[x] && x != a && d.get(a, x) < distance {
+                best = Some(x);
+                distance = d.get(a, x);
+            }
+        }
+
+        let b = match best {
+            Some(b) if distance < std::f64::INFINITY => b,
+            _ => {
+                // Nothing is reachable from here.
+                active[a] = false;
+                remaining -= 1;
+                chain.clear();
+                continue;
+            },
+        };
+
+        if Some(b) != previous {
+            chain.push(b);
+            continue;
+        }
+
+        chain.pop();
+        chain.pop();
+
+        let (na, nb) = (size[a] as f64, size[b] as f64);
+
+        for k in 0..n {
+            if !active[k] || k == a || k == b {
+                continue;
+            }
+
+            let (da, db) = (d.get(a, k), d.get(b, k));
+            let nk = size[k] as f64;
+            let merged = match linkage {
+                Linkage::Complete => if da > db { da } else { db },
+                Linkage::Average => (na * da + nb * db) / (na + nb),
+                _ => ((na + nk) * da + (nb + nk) * db - nk * distance) / (na + nb + nk),
+            };
+
+            d.set(b, k, merged);
+        }
+
+        active[a] = false;
+        size[b] += size[a];
+        remaining -= 1;
+        found.push((a, b, if ward { distance.sqrt() } else { distance }));
+    }
+
+    // The chain finds merges out of order, from_edges sorts them by height.
+    Ok(Dendrogram::from_edges(n, found))
+}
+
+// As cluster_spacing, for any linkage: the clustering cut at the given number of groups.
+pub fn linkage_clusters(groups: usize, g: &Graph, linkage: Linkage) -> Result<Cut, &'static str> {
+    agglomerate(g, linkage).map(|d| d.cut(groups))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use {Graph, Edge};
+    use fixtures::line;
+
+    // Merges by brute force over all cluster pairs, for comparison.
+    fn naive(g: &Graph, linkage: Linkage) -> Vec<f64> {
+        let n = g.vertices.len();
+        let mut dist = vec![vec![0.0; n]; n];
+        let mut clusters: Vec<Vec<usize>> = (0..n).map(|v| vec![v]).collect();
+        let mut heights = vec![];
+
+        for v in &g.vertices {
+            for e in &v.edges {
+                dist[e.head][e.tail] = e.cost as f64;
+            }
+        }
+
+        while clusters.len() > 1 {
+            let mut best = (std::f64::INFINITY, 0, 0);
+
+            for i in 0..clusters.len() {
+                for j in (i + 1)..clusters.len() {
+                    let pairs: Vec<f64> = clusters[i].iter()
+                        .flat_map(|&a| clusters[j].iter().map(|&b| dist[a][b]).collect::<Vec<f64>>())
+                        .collect();
+                    let h = match linkage {
+                        Linkage::Complete => pairs.iter().cloned().fold(0.0, f64::max),
+                        _ => pairs.iter().sum::<f64>() / pairs.len() as f64,
+                    };
+
+                    if h < best.0 {
+                        best = (h, i, j);
+                    }
+                }
+            }
+
+            let j = clusters.remove(best.2);
+
+            clusters[best.1].extend(j);
+            heights.push(best.0);
+        }
+
+        heights
+    }
+
+    #[test]
+    fn complete() {
+        let g = line(&[0, 1, 3, 10, 12]);
+        let d = agglomerate(&g, Linkage::Complete).ok().unwrap();
+        let heights: Vec<f64> = d.merges().iter().map(|m| m.height).collect();
+
+        assert_eq!(heights, vec![1.0, 2.0, 3.0, 12.0]);
+        assert_eq!(d.cut(2).members, vec![vec![0, 1, 2], vec![3, 4]]);
+        assert_eq!((d.merges()[2].left, d.merges()[2].right, d.merges()[2].size), (2, 5, 3));
+    }
+
+    #[test]
+    fn average() {
+        let g = line(&[0, 1, 3, 10, 12]);
+        let heights: Vec<f64> = agglomerate(&g, Linkage::Average).ok().unwrap().merges().iter().map(|m| m.height).collect();
+
+        assert_eq!(heights, vec![1.0, 2.0, 2.5, 58.0 / 6.0]);
+    }
+
+    #[test]
+    fn ward() {
+        let g = line(&[0, 2, 10]);
+        let d = agglomerate(&g, Linkage::Ward).ok().unwrap();
+
+        // As in SciPy, joining two vertices costs their distance. The centroid 1 of the pair
+        // against 10 then gives sqrt(2 * 2 * 1 / 3) * 9.
+        assert_eq!(d.merges()[0].height, 2.0);
+        assert!((d.merges()[1].height - (4.0f64 / 3.0).sqrt() * 9.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn matches_naive() {
+        // A Golomb ruler, so no two pairs tie and the merge order is unique.
+        let g = line(&[0, 2, 6, 24, 29, 40, 43, 55, 68, 75, 76, 85]);
+
+        for &linkage in &[Linkage::Complete, Linkage::Average] {
+            let heights: Vec<f64> = agglomerate(&g, linkage).ok().unwrap().merges().iter().map(|m| m.height).collect();
+            let expected = naive(&g, linkage);
+
+            for (h, e) in heights.iter().zip(expected.iter()) {
+                assert!((h - e).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn disconnected() {
+        let mut g = Graph::new();
+
+        g.build(3);
+        g.vertices[0].edges.push(Edge{head: 0, tail: 1, cost: 4});
+        g.vertices[1].edges.push(Edge{head: 1, tail: 0, cost: 4});
+
+        let d = agglomerate(&g, Linkage::Average).ok().unwrap();
+
+        assert_eq!(d.merges().len(), 1);
+        assert_eq!(d.cut(1).clusters(), 2);
+    }
+
+    #[test]
+    fn execute() {
+        let p = Path::new("clustering1.txt");
+        let g = Graph::from_file(p).ok().unwrap();
+
+        assert_eq!(linkage_clusters(4, &g, Linkage::Single).ok().unwrap().spacing, Some(106.0));
+
+        for &linkage in &[Linkage::Complete, Linkage::Average, Linkage::Ward] {
+            let d = agglomerate(&g, linkage).ok().unwrap();
+
+            assert_eq!(d.merges().len(), g.vertices.len() - 1);
+            assert!(d.merges().windows(2).all(|w| w[0].height <= w[1].height));
+            assert_eq!(linkage_clusters(4, &g, linkage).ok().unwrap().clusters(), 4);
+        }
+
+        let mut big = Graph::new();
+
+        big.build(MAX_VERTICES as i32 + 1);
+
+        assert!(agglomerate(&big, Linkage::Average).is_err());
+        assert_eq!(agglomerate(&big, Linkage::Single).ok().unwrap().leaves(), MAX_VERTICES + 1);
+    }
+}
@@ -0,0 +1,232 @@
+use points::Coordinates;
+use rng::Rng;
+
+// k clusters each centred on one of the points, its medoid. Cost is the total distance from
+// every point to its medoid.
+#[derive(Debug, Clone)]
+pub struct Medoids {
+    pub medoids: Vec<usize>,
+    pub assignment: Vec<usize>,
+    pub cost: f64,
+}
+
+impl Medoids {
+    // Assigns every point to its nearest medoid, the first on ties.
+    fn new(c: &Coordinates, medoids: Vec<usize>) -> Medoids {
+        let mut assignment = vec![0; c.len()];
+        let mut cost = 0.0;
+
+        for x in 0..c.len() {
+            let mut best = std::f64::INFINITY;
+
+            for (j, &m) in medoids.iter().enumerate() {
+                let d = c.distance(x, m);
+
+                if d < best {
+                    best = d;
+                    assignment[x] = j;
+                }
+            }
+
+            cost += best;
+        }
+
+        Medoids{medoids: medoids, assignment: assignment, cost: cost}
+    }
+
+    pub fn members(&self) -> Vec<Vec<usize>> {
+        let mut members = vec![vec![]; self.medoids.len()];
+
+        for (x, &j) in self.assignment.iter().enumerate() {
+            members[j].push(x);
+        }
+
+        members
+    }
+}
+
+// PAM over the given points: BUILD picks medoids greedily, then SWAP makes the best
+// medoid/non-medoid exchange until none lowers the cost. Each pass costs O(k(n - k)n), with
+// the nearest and second nearest medoid of every point kept to price a swap in O(n).
+fn pam_on(c: &Coordinates, points: &[usize], k: usize) -> Vec<usize> {
+    let n = points.len();
+    let d: Vec<Vec<f64>> = points.iter().map(|&a| points.iter().map(|&b| c.distance(a, b)).collect()).collect();
+    let mut medoids: Vec<usize> = vec![];
+    let mut nearest = vec![std::f64::INFINITY; n];
+
+    // BUILD: each new medoid is the one that lowers the cost most.
+    while medoids.len() < k {
+        let mut best = (std::f64::INFINITY, 0);
+
+        for h in (0..n).filter(|h| !medoids.contains(h)) {
+            let cost: f64 = (0..n).map(|o| if d[o][h] < nearest[o] { d[o][h] } else { nearest[o] }).sum();
+
+            if cost < best.0 {
+                best = (cost, h);
+            }
+        }
+
+        medoids.push(best.1);
+
+        for o in 0..n {
+            if d[o][best.1] < nearest[o] {
+                nearest[o] = d[o][best.1];
+            }
+        }
+    }
+
+    // SWAP.
+    loop {
+        let mut first = vec![0; n];
+        let mut second = vec![std::f64::INFINITY; n];
+
+        for o in 0..n {
+            let mut best = std::f64::INFINITY;
+
+            for (j, &m) in medoids.iter().enumerate() {
+                if d[o][m] < best {
+                    second[o] = best;
+                    best = d[o][m];
+                    first[o] = j;
+                } else if d[o][m] < second[o] {
+                    second[o] = d[o][m];
+                }
+            }
+
+            nearest[o] = best;
+        }
+
+        let mut best = (-1e-9, 0, 0);
+
+        for j in 0..k {
+            for h in (0..n).filter(|h| !medoids.contains(h)) {
+                let mut delta = 0.0;
+
+                for o in 0..n {
+                    delta += if first[o] == j {
+                        (if d[o][h] < second[o] { d[o][h] } else { second[o] }) - nearest[o]
+                    } else if d[o][h] < nearest[o] {
+                        d[o][h] - nearest[o]
+                    } else {
+                        0.0
+                    };
+                }
+
+                if delta < best.0 {
+                    best = (delta, j, h);
+                }
+            }
+        }
+
+        if best.0 >= -1e-9 {
+            break;
+        }
+
+        medoids[best.1] = best.2;
+    }
+
+    let mut result: Vec<usize> = medoids.iter().map(|&m| points[m]).collect();
+
+    result.sort();
+
+    result
+}
+
+// Partitioning Around Medoids over all the points. Deterministic, but quadratic in memory
+// and at least quadratic in time, so CLARA is the choice beyond a few thousand points.
+pub fn pam(c: &Coordinates, k: usize) -> Result<Medoids, &'static str> {
+    if k == 0 || k > c.len() {
+        return Err("Invalid k");
+    }
+
+    let all: Vec<usize> = (0..c.len()).collect();
+
+    Ok(Medoids::new(c, pam_on(c, &all, k)))
+}
+
+// CLARA: PAM on several random samples, keeping whichever medoids cost least over all the
+// points.
+pub fn clara(c: &Coordinates, k: usize, samples: usize, sample_size: usize, rng: &mut Rng) -> Result<Medoids, &'static str> {
+    if k == 0 || k > c.len() {
+        return Err("Invalid k");
+    }
+
+    let mut best: Option<Medoids> = None;
+
+    for _ in 0..std::cmp::max(samples, 1) {
+        let mut sample = rng.sample(c.len(), std::cmp::max(sample_size, k));
+
+        sample.sort();
+
+        let candidate = Medoids::new(c, pam_on(c, &sample, k));
+
+        if best.as_ref().map_or(true, |b| candidate.cost < b.cost) {
+            best = Some(candidate);
+        }
+    }
+
+    Ok(best.unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use points::Coordinates;
+    use rng::Rng;
+
+    fn brute_force(c: &Coordinates, k: usize) -> f64 {
+        let n = c.len();
+        let mut best = std::f64::INFINITY;
+
+        for bits in 0..(1usize << n) {
+            if bits.count_ones() as usize != k {
+                continue;
+            }
+
+            let medoids: Vec<usize> = (0..n).filter(|&x| bits & (1 << x) != 0).collect();
+            let cost = Medoids::new(c, medoids).cost;
+
+            if cost < best {
+                best = cost;
+            }
+        }
+
+        best
+    }
+
+    #[test]
+    fn simple1() {
+        let c = Coordinates::from_file(Path::new("points_simple1.txt")).ok().unwrap();
+        let m = pam(&c, 3).ok().unwrap();
+
+        assert_eq!(m.medoids.len(), 3);
+        assert_eq!(m.members().iter().map(|m| m.len()).collect::<Vec<usize>>(), vec![3, 3, 2]);
+        assert!((m.cost - brute_force(&c, 3)).abs() < 1e-9);
+        assert!(pam(&c, 0).is_err());
+    }
+
+    #[test]
+    fn near_optimal() {
+        let mut rng = Rng::new(4);
+        let c = Coordinates::new(2, (0..28).map(|_| rng.next_f64() * 100.0).collect());
+
+        // PAM is a local search, but on 14 points it should find the optimum.
+        for k in 1..5 {
+            assert!((pam(&c, k).ok().unwrap().cost - brute_force(&c, k)).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn clara_samples() {
+        let mut rng = Rng::new(8);
+        let values: Vec<f64> = (0..400).map(|i| if i < 200 { rng.next_f64() } else { 50.0 + rng.next_f64() }).collect();
+        let c = Coordinates::new(2, values);
+        let a = clara(&c, 2, 5, 40, &mut Rng::new(1)).ok().unwrap();
+        let b = clara(&c, 2, 5, 40, &mut Rng::new(1)).ok().unwrap();
+
+        assert_eq!(a.medoids, b.medoids);
+        assert_eq!(a.members().iter().map(|m| m.len()).collect::<Vec<usize>>(), vec![100, 100]);
+        assert!(a.cost < pam(&c, 2).ok().unwrap().cost * 1.5);
+    }
+}
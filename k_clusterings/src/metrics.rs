@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+use Graph;
+use Clustering;
+
+// The graph as a distance matrix. Vertex pairs without an edge are infinitely far apart.
+fn distances(g: &Graph) -> Vec<Vec<f64>> {
+    let n = g.vertices.len();
+    let mut d = vec![vec![std::f64::INFINITY; n]; n];
+
+    for i in 0..n {
+        d[i][i] = 0.0;
+    }
+
+    for v in &g.vertices {
+        for e in &v.edges {
+            let cost = e.cost as f64;
+
+            if cost < d[e.head][e.tail] {
+                d[e.head][e.tail] = cost;
+                d[e.tail][e.head] = cost;
+            }
+        }
+    }
+
+    d
+}
+
+fn check(g: &Graph, c: &Clustering) -> Result<(), &'static str> {
+    if c.cluster.len() != g.vertices.len() {
+        return Err("Clustering does not match graph");
+    }
+
+    if c.clusters() < 2 {
+        return Err("Fewer than two clusters");
+    }
+
+    Ok(())
+}
+
+// The smallest distance between two vertices in different clusters. For single linkage this
+// is exactly the spacing.
+pub fn separation(g: &Graph, c: &Clustering) -> Result<f64, &'static str> {
+    try!(check(g, c));
+
+    let d = distances(g);
+    let mut smallest = std::f64::INFINITY;
+
+    for a in 0..d.len() {
+        for b in (a + 1)..d.len() {
+            if c.cluster[a] != c.cluster[b] && d[a][b] < smallest {
+                smallest = d[a][b];
+            }
+        }
+    }
+
+    Ok(smallest)
+}
+
+// The largest distance between two vertices in the same cluster.
+pub fn diameter(g: &Graph, c: &Clustering) -> Result<f64, &'static str> {
+    try!(check(g, c));
+
+    let d = distances(g);
+    let mut largest = 0.0;
+
+    for a in 0..d.len() {
+        for b in (a + 1)..d.len() {
+            if c.cluster[a] == c.cluster[b] && d[a][b] > largest {
+                largest = d[a][b];
+            }
+        }
+    }
+
+    Ok(largest)
+}
+
+// Separation over diameter. Higher is better.
+pub fn dunn(g: &Graph, c: &Clustering) -> Result<f64, &'static str> {
+    let separation = try!(separation(g, c));
+    let diameter = try!(diameter(g, c));
+
+    if diameter == 0.0 {
+        return Ok(std::f64::INFINITY);
+    }
+
+    Ok(separation / diameter)
+}
+
+// The mean silhouette over all vertices, between -1 and 1. Higher is better. A vertex alone
+// in its cluster scores 0.
+pub fn silhouette(g: &Graph, c: &Clustering) -> Result<f64, &'static str> {
+    try!(check(g, c));
+
+    let d = distances(g);
+    let k = c.clusters();
+    let mut total = 0.0;
+
+    for x in 0..d.len() {
+        let own = c.cluster[x];
+
+        if c.members[own].len() == 1 {
+            continue;
+        }
+
+        let mut sums = vec![0.0; k];
+
+        for y in 0..d.len() {
+            sums[c.cluster[y]] += d[x][y];
+        }
+
+        let a = sums[own] / (c.members[own].len() - 1) as f64;
+        let b = (0..k)
+            .filter(|&j| j != own)
+            .map(|j| sums[j] / c.members[j].len() as f64)
+            .fold(std::f64::INFINITY, f64::min);
+        let larger = if a > b { a } else { b };
+
+        if larger > 0.0 && larger.is_finite() {
+            total += (b - a) / larger;
+        }
+    }
+
+    Ok(total / d.len() as f64)
+}
+
+// Davies-Bouldin, with each cluster's medoid standing in for the centroid a graph does not
+// have. Lower is better.
+pub fn davies_bouldin(g: &Graph, c: &Clustering) -> Result<f64, &'static str> {
+    try!(check(g, c));
+
+    let d = distances(g);
+    let mut medoids = vec![];
+    let mut scatter = vec![];
+
+    for m in &c.members {
+        let (cost, medoid) = m.iter()
+            .map(|&x| (m.iter().map(|&y| d[x][y]).sum::<f64>(), x))
+            .fold((std::f64::INFINITY, m[0]), |best, next| if next.0 < best.0 { next } else { best });
+
+        medoids.push(medoid);
+        scatter.push(cost / m.len() as f64);
+    }
+
+    let k = c.clusters();
+    let mut total = 0.0;
+
+    for i in 0..k {
+        if (0..k).any(|j| j != i && d[medoids[i]][medoids[j]] == 0.0) {
+            return Err("Two medoids at distance 0");
+        }
+
+        let worst = (0..k)
+            .filter(|&j| j != i)
+            .map(|j| (scatter[i] + scatter[j]) / d[medoids[i]][medoids[j]])
+            .fold(0.0, f64::max);
+
+        total += worst;
+    }
+
+    Ok(total / k as f64)
+}
+
+// Pair counts of how two labellings overlap.
+fn contingency(a: &[usize], b: &[usize]) -> Result<(HashMap<(usize, usize), usize>, HashMap<usize, usize>, HashMap<usize, usize>), &'static str> {
+    if a.len() != b.len() {
+        return Err("Labellings differ in length");
+    }
+
+    let mut table = HashMap::new();
+    let mut rows = HashMap::new();
+    let mut columns = HashMap::new();
+
+    for (&x, &y) in a.iter().zip(b) {
+        *table.entry((x, y)).or_insert(0) += 1;
+        *rows.entry(x).or_insert(0) += 1;
+        *columns.entry(y).or_insert(0) += 1;
+    }
+
+    Ok((table, rows, columns))
+}
+
+fn pairs(n: usize) -> f64 {
+    n as f64 * (n as f64 - 1.0) / 2.0
+}
+
+// The adjusted Rand index of two labellings, such as a clustering against the ground truth.
+// 1 when they agree, around 0 for chance. Needs at least two labels to have a pair.
+pub fn adjusted_rand(a: &[usize], b: &[usize]) -> Result<f64, &'static str> {
+    let (table, rows, columns) = try!(contingency(a, b));
+
+    if a.len() < 2 {
+        return Err("Fewer than two labels");
+    }
+
+    let index: f64 = table.values().map(|&n| pairs(n)).sum();
+    let row: f64 = rows.values().map(|&n| pairs(n)).sum();
+    let column: f64 = columns.values().map(|&n| pairs(n)).sum();
+    let expected = row * column / pairs(a.len());
+    let most = (row + column) / 2.0;
+
+    if most == expected {
+        return Ok(1.0);
+    }
+
+    Ok((index - expected) / (most - expected))
+}
+
+// Normalised mutual information of two labellings, over the mean of their entropies.
+// Between 0 and 1.
+pub fn nmi(a: &[usize], b: &[usize]) -> Result<f64, &'static str> {
+    let (table, rows, columns) = try!(contingency(a, b));
+    let n = a.len() as f64;
+    let entropy = |counts: &HashMap<usize, usize>| -> f64 {
+        counts.values().map(|&c| { let p = c as f64 / n; -p * p.ln() }).sum()
+    };
+    let (ha, hb) = (entropy(&rows), entropy(&columns));
+
+    if ha == 0.0 && hb == 0.0 {
+        return Ok(1.0);
+    }
+
+    let mutual: f64 = table.iter()
+        .map(|(&(x, y), &c)| {
+            let p = c as f64 / n;
+
+            p * (p * n * n / (rows[&x] as f64 * columns[&y] as f64)).ln()
+        })
+        .sum();
+
+    Ok(mutual / ((ha + hb) / 2.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use {Graph, cluster_spacing};
+    use fixtures::line;
+
+    #[test]
+    fn spacing_is_separation() {
+        let p = Path::new("clustering1.txt");
+        let mut g = Graph::from_file(p).ok().unwrap();
+        let c = cluster_spacing(4, &mut g);
+
+        assert_eq!(separation(&g, &c).ok().unwrap(), 106.0);
+        assert!(dunn(&g, &c).ok().unwrap() > 0.0);
+    }
+
+    #[test]
+    fn simple() {
+        let mut g = line(&[0, 1, 10, 12]);
+        let c = cluster_spacing(2, &mut g);
+
+        assert_eq!(c.members, vec![vec![0, 1], vec![2, 3]]);
+        assert_eq!(diameter(&g, &c).ok().unwrap(), 2.0);
+        assert_eq!(dunn(&g, &c).ok().unwrap(), 9.0 / 2.0);
+
+        // Vertex 0: a = 1, b = 11. Vertex 1: a = 1, b = 10. Vertex 2: a = 2, b = 9.5.
+        // Vertex 3: a = 2, b = 11.5.
+        let expected = (10.0 / 11.0 + 9.0 / 10.0 + 7.5 / 9.5 + 9.5 / 11.5) / 4.0;
+
+        assert!((silhouette(&g, &c).ok().unwrap() - expected).abs() < 1e-12);
+
+        // Medoids 0 and 2 with scatter 1/2 and 2/2, 10 apart.
+        assert!((davies_bouldin(&g, &c).ok().unwrap() - 0.15).abs() < 1e-12);
+
+        let one = cluster_spacing(1, &mut g);
+
+        assert!(silhouette(&g, &one).is_err());
+        assert!(diameter(&g, &one).is_err());
+
+        // Every vertex in the same place, so the medoids coincide.
+        let g = line(&[5, 5, 5, 5]);
+        let c = Clustering{cluster: vec![0, 0, 1, 1], members: vec![vec![0, 1], vec![2, 3]], spacing: 0, merged: vec![]};
+
+        assert!(davies_bouldin(&g, &c).is_err());
+    }
+
+    #[test]
+    fn agreement() {
+        let truth = [0, 0, 0, 1, 1, 1];
+        let renamed = [5, 5, 5, 2, 2, 2];
+        let split = [0, 0, 1, 1, 2, 2];
+
+        assert_eq!(adjusted_rand(&truth, &renamed).ok().unwrap(), 1.0);
+        assert!((nmi(&truth, &renamed).ok().unwrap() - 1.0).abs() < 1e-12);
+
+        // Two agreeing pairs against 1.2 expected by chance and 4.5 at most.
+        assert!((adjusted_rand(&truth, &split).ok().unwrap() - 0.8 / 3.3).abs() < 1e-12);
+        assert!(nmi(&truth, &split).ok().unwrap() < 1.0);
+        assert!(adjusted_rand(&truth, &split[..5]).is_err());
+        assert!(adjusted_rand(&truth[..1], &split[..1]).is_err());
+        assert!(adjusted_rand(&[], &[]).is_err());
+    }
+}
@@ -0,0 +1,493 @@
+use std::path::Path;
+use std::io::BufReader;
+use std::io::BufRead;
+use std::io::{Error, ErrorKind};
+use std::fs::File;
+
+use dendrogram::{Cut, Dendrogram};
+use union_find::UnionFind;
+
+// Points in any number of dimensions, stored one after another.
+#[derive(Debug, Clone)]
+pub struct Coordinates {
+    dimensions: usize,
+    values: Vec<f64>,
+}
+
+impl Coordinates {
+    pub fn new(dimensions: usize, values: Vec<f64>) -> Coordinates {
+        if dimensions == 0 || values.len() % dimensions != 0 {
+            panic!("Invalid coordinates: {:?} values in {:?} dimensions", values.len(), dimensions);
+        }
+
+        if let Some(v) = values.iter().find(|v| !v.is_finite()) {
+            panic!("Invalid coordinate: {:?}", v);
+        }
+
+        Coordinates{dimensions: dimensions, values: values}
+    }
+
+    // Reads the tsp format: the number of points, then one point per line as space separated
+    // coordinates. The first point sets the number of dimensions. NaN and infinite coordinates
+    // are rejected, as distances between them cannot be ordered.
+    pub fn from_file(path: &Path) -> Result<Coordinates, Error> {
+        let file = try!(File::open(path));
+        let mut buffer = BufReader::new(&file);
+        let mut first_line = String::new();
+
+        try!(buffer.read_line(&mut first_line));
+
+        let size = match first_line.trim().parse::<usize>() {
+            Ok(size) => size,
+            Err(_) => return Err(Error::new(ErrorKind::InvalidData, "Invalid header")),
+        };
+        let mut dimensions = 0;
+        let mut values = vec![];
+
+        for l in buffer.lines() {
+            let line = try!(l);
+            let mut point = vec![];
+
+            for d in line.split_whitespace() {
+                match d.parse::<f64>() {
+                    Ok(v) if v.is_finite() => point.push(v),
+                    _ => return Err(Error::new(ErrorKind::InvalidData, "Invalid coordinate")),
+                }
+            }
+
+            if point.is_empty() {
+                continue;
+            }
+
+            if dimensions == 0 {
+                dimensions = point.len();
+            } else if point.len() != dimensions {
+                return Err(Error::new(ErrorKind::InvalidData, "Wrong number of dimensions"));
+            }
+
+            values.extend(point);
+        }
+
+        if dimensions == 0 || values.len() / dimensions != size {
+            return Err(Error::new(ErrorKind::InvalidData, "Wrong number of points"));
+        }
+
+        Ok(Coordinates{dimensions: dimensions, values: values})
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len() / self.dimensions
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    pub fn point(&self, i: usize) -> &[f64] {
+        &self.values[i * self.dimensions..(i + 1) * self.dimensions]
+    }
+
+    fn squared_distance(&self, a: usize, b: usize) -> f64 {
+        squared(self.point(a), self.point(b))
+    }
+
+    pub fn distance(&self, a: usize, b: usize) -> f64 {
+        self.squared_distance(a, b).sqrt()
+    }
+}
+
+const LEAF_SIZE: usize = 8;
+
+// A node of the k-d tree covers order[start..end] and their bounding box. Children come
+// after their parent, so a backwards sweep sees them first.
+#[derive(Debug)]
+struct Node {
+    start: usize,
+    end: usize,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+    children: Option<(usize, usize)>,
+}
+
+// A k-d tree over a set of coordinates, for neighbourhood queries.
+#[derive(Debug)]
+pub struct KdTree {
+    order: Vec<usize>,
+    nodes: Vec<Node>,
+}
+
+impl KdTree {
+    pub fn new(c: &Coordinates) -> KdTree {
+        let mut tree = KdTree{order: (0..c.len()).collect(), nodes: vec![]};
+        let mut pending = vec![];
+
+        if !c.is_empty() {
+            pending.push(tree.add(c, 0, c.len()));
+        }
+
+        // Splits each node across its widest dimension at the median.
+        while let Some(id) = pending.pop() {
+            let (start, end) = (tree.nodes[id].start, tree.nodes[id].end);
+
+            if end - start <= LEAF_SIZE {
+                continue;
+            }
+
+            let widest = (0..c.dimensions())
+                .max_by(|&x, &y| {
+                    let ref n = tree.nodes[id];
+
+                    (n.upper[x] - n.lower[x]).partial_cmp(&(n.upper[y] - n.lower[y])).unwrap()
+                })
+                .unwrap();
+            let middle = start + (end - start) / 2;
+
+            tree.order[start..end].sort_by(|&a, &b| c.point(a)[widest].partial_cmp(&c.point(b)[widest]).unwrap());
+
+            let left = tree.add(c, start, middle);
+            let right = tree.add(c, middle, end);
+
+            tree.nodes[id].children = Some((left, right));
+            pending.push(left);
+            pending.push(right);
+        }
+
+        tree
+    }
+
+    fn add(&mut self, c: &Coordinates, start: usize, end: usize) -> usize {
+        let mut lower = c.point(self.order[start]).to_vec();
+        let mut upper = lower.clone();
+
+        for &p in &self.order[start..end] {
+            for (d, &x) in c.point(p).iter().enumerate() {
+                if x < lower[d] { lower[d] = x; }
+                if x > upper[d] { upper[d] = x; }
+            }
+        }
+
+        self.nodes.push(Node{start: start, end: end, lower: lower, upper: upper, children: None});
+
+        self.nodes.len() - 1
+    }
+
+    fn squared_distance_to(&self, id: usize, point: &[f64]) -> f64 {
+        let ref n = self.nodes[id];
+
+        point.iter().enumerate()
+            .map(|(d, &x)| {
+                let gap = if x < n.lower[d] { n.lower[d] - x } else if x > n.upper[d] { x - n.upper[d] } else { 0.0 };
+
+                gap * gap
+            })
+            .sum()
+    }
+
+    // Every point within radius of the given one, in order.
+    pub fn within(&self, c: &Coordinates, point: &[f64], radius: f64) -> Vec<usize> {
+        let mut found = vec![];
+        let mut stack = if self.nodes.is_empty() { vec![] } else { vec![0] };
+
+        while let Some(id) = stack.pop() {
+            if self.squared_distance_to(id, point) > radius * radius {
+                continue;
+            }
+
+            let ref node = self.nodes[id];
+
+            match node.children {
+                Some((l, r)) => {
+                    stack.push(l);
+                    stack.push(r);
+                },
+                None => {
+                    for &q in &self.order[node.start..node.end] {
+                        if squared(c.point(q), point) <= radius * radius {
+                            found.push(q);
+                        }
+                    }
+                },
+            }
+        }
+
+        found.sort();
+
+        found
+    }
+
+    // The k points nearest the given one as (distance, point), nearest first.
+    pub fn nearest(&self, c: &Coordinates, point: &[f64], k: usize) -> Vec<(f64, usize)> {
+        let mut best: Vec<(f64, usize)> = vec![];
+        let mut stack = if self.nodes.is_empty() || k == 0 { vec![] } else { vec![0] };
+
+        while let Some(id) = stack.pop() {
+            if best.len() == k && self.squared_distance_to(id, point) > best[k - 1].0 {
+                continue;
+            }
+
+            let ref node = self.nodes[id];
+
+            match node.children {
+                Some((l, r)) => {
+                    if self.squared_distance_to(l, point) < self.squared_distance_to(r, point) {
+                        stack.push(r);
+                        stack.push(l);
+                    } else {
+                        stack.push(l);
+                        stack.push(r);
+                    }
+                },
+                None => {
+                    for &q in &self.order[node.start..node.end] {
+                        let candidate = (squared(c.point(q), point), q);
+
+                        if best.len() < k || candidate < best[k - 1] {
+                            let at = best.iter().position(|b| candidate < *b).unwrap_or(best.len());
+
+                            best.insert(at, candidate);
+                            best.truncate(k);
+                        }
+                    }
+                },
+            }
+        }
+
+        best.iter().map(|&(d, q)| (d.sqrt(), q)).collect()
+    }
+}
+
+fn squared(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+// The Euclidean minimum spanning tree by Borůvka's algorithm: every round, each component
+// finds its nearest point outside itself through the k-d tree, then all those edges join.
+// Subtrees lying entirely in the searching point's component are skipped, so no complete
+// graph is ever built. Edges are (a, b, distance).
+pub fn euclidean_mst(c: &Coordinates) -> Vec<(usize, usize, f64)> {
+    reachability_mst(c, &vec![0.0; c.len()])
+}
+
+// The minimum spanning tree under mutual reachability, where a and b are
+// max(core[a], core[b], distance(a, b)) apart. With every core distance 0 it is the
+// Euclidean minimum spanning tree.
+pub fn reachability_mst(c: &Coordinates, core: &[f64]) -> Vec<(usize, usize, f64)> {
+    let n = c.len();
+    let core: Vec<f64> = core.iter().map(|d| d * d).collect();
+    let tree = KdTree::new(c);
+    let mut clusters = UnionFind::new(n);
+    let mut edges = vec![];
+    let none = std::usize::MAX;
+
+    while clusters.components() > 1 {
+        let component = clusters.leaders();
+
+        // The component all of a node's points share, if they do.
+        let mut shared = vec![none; tree.nodes.len()];
+
+        for id in (0..tree.nodes.len()).rev() {
+            let ref node = tree.nodes[id];
+
+            shared[id] = match node.children {
+                Some((l, r)) => if shared[l] == shared[r] { shared[l] } else { none },
+                None => {
+                    let first = component[tree.order[node.start]];
+
+                    if tree.order[node.start..node.end].iter().all(|&p| component[p] == first) { first } else { none }
+                },
+            };
+        }
+
+        // The cheapest edge out of each component, ties broken by the vertices so no round
+        // can pick a cycle.
+        let mut best: Vec<Option<(f64, usize, usize)>> = vec![None; n];
+
+        for p in 0..n {
+            let own = component[p];
+            let point = c.point(p);
+            let mut stack = vec![0];
+
+            while let Some(id) = stack.pop() {
+                let bound = best[own].map_or(std::f64::INFINITY, |b| b.0);
+
+                if shared[id] == own || core[p] > bound || tree.squared_distance_to(id, point) > bound {
+                    continue;
+                }
+
+                let ref node = tree.nodes[id];
+
+                match node.children {
+                    Some((l, r)) => {
+                        // Visit the nearer child first so the bound tightens sooner.
+                        if tree.squared_distance_to(l, point) < tree.squared_distance_to(r, point) {
+                            stack.push(r);
+                            stack.push(l);
+                        } else {
+                            stack.push(l);
+                            stack.push(r);
+                        }
+                    },
+                    None => {
+                        for &q in &tree.order[node.start..node.end] {
+                            if component[q] == own {
+                                continue;
+                            }
+
+                            let reach = c.squared_distance(p, q).max(core[p]).max(core[q]);
+                            let candidate = (reach, std::cmp::min(p, q), std::cmp::max(p, q));
+
+                            if best[own].map_or(true, |b| candidate < b) {
+                                best[own] = Some(candidate);
+                            }
+                        }
+                    },
+                }
+            }
+        }
+
+        let mut joined = false;
+
+        for b in best.iter().filter_map(|&b| b) {
+            if clusters.union(b.1, b.2) {
+                edges.push((b.1, b.2, b.0.sqrt()));
+                joined = true;
+            }
+        }
+
+        if !joined {
+            break;
+        }
+    }
+
+    edges
+}
+
+// Single-linkage clustering of the points, from their Euclidean minimum spanning tree.
+pub fn point_dendrogram(c: &Coordinates) -> Dendrogram {
+    Dendrogram::from_edges(c.len(), euclidean_mst(c))
+}
+
+// As cluster_spacing, straight from coordinates.
+pub fn point_clusters(groups: usize, c: &Coordinates) -> Cut {
+    point_dendrogram(c).cut(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use rng::Rng;
+
+    fn random(size: usize, dimensions: usize, seed: u64) -> Coordinates {
+        let mut rng = Rng::new(seed);
+
+        Coordinates::new(dimensions, (0..size * dimensions).map(|_| rng.below(1000) as f64 / 10.0).collect())
+    }
+
+    // Prim's algorithm over the complete graph.
+    fn prim(c: &Coordinates) -> f64 {
+        let n = c.len();
+        let mut done = vec![false; n];
+        let mut nearest = vec![std::f64::INFINITY; n];
+        let mut total = 0.0;
+
+        nearest[0] = 0.0;
+
+        for _ in 0..n {
+            let v = (0..n).filter(|&v| !done[v]).min_by(|&a, &b| nearest[a].partial_cmp(&nearest[b]).unwrap()).unwrap();
+
+            done[v] = true;
+            total += nearest[v];
+
+            for w in 0..n {
+                if !done[w] && c.distance(v, w) < nearest[w] {
+                    nearest[w] = c.distance(v, w);
+                }
+            }
+        }
+
+        total
+    }
+
+    #[test]
+    fn representation() {
+        let c = Coordinates::from_file(Path::new("points_simple1.txt")).ok().unwrap();
+
+        assert_eq!(c.len(), 8);
+        assert_eq!(c.dimensions(), 2);
+        assert_eq!(c.point(1), &[2.0, 1.0]);
+        assert_eq!(c.distance(0, 1), 1.0);
+    }
+
+    #[test]
+    fn non_finite() {
+        let p = std::env::temp_dir().join(format!("k_clusterings-{}-non-finite.txt", std::process::id()));
+
+        for contents in &["1\nNaN 4\n", "2\n1 2\ninf 3\n"] {
+            std::fs::write(&p, contents).ok().unwrap();
+            assert_eq!(Coordinates::from_file(&p).err().unwrap().kind(), ErrorKind::InvalidData);
+        }
+
+        std::fs::remove_file(&p).ok().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn non_finite_values() {
+        Coordinates::new(2, vec![1.0, std::f64::NAN]);
+    }
+
+    #[test]
+    fn simple1() {
+        let c = Coordinates::from_file(Path::new("points_simple1.txt")).ok().unwrap();
+        let clustering = point_clusters(3, &c);
+
+        assert_eq!(clustering.members, vec![vec![0, 1, 2], vec![3, 4, 5], vec![6, 7]]);
+        assert_eq!(point_dendrogram(&c).merges().len(), 7);
+    }
+
+    #[test]
+    fn matches_prim() {
+        for &(size, dimensions) in &[(300, 2), (200, 3), (100, 5), (5, 1)] {
+            let c = random(size, dimensions, size as u64);
+            let edges = euclidean_mst(&c);
+            let total: f64 = edges.iter().map(|e| e.2).sum();
+
+            assert_eq!(edges.len(), size - 1);
+            assert!((total - prim(&c)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn queries() {
+        let c = random(500, 3, 17);
+        let tree = KdTree::new(&c);
+        let point = c.point(42);
+
+        let within = tree.within(&c, point, 15.0);
+        let expected: Vec<usize> = (0..c.len()).filter(|&q| c.distance(42, q) <= 15.0).collect();
+
+        assert_eq!(within, expected);
+
+        let nearest = tree.nearest(&c, point, 6);
+        let mut all: Vec<(f64, usize)> = (0..c.len()).map(|q| (c.distance(42, q), q)).collect();
+
+        all.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        assert_eq!(nearest[0], (0.0, 42));
+        assert_eq!(nearest.iter().map(|n| n.1).collect::<Vec<usize>>(), all[..6].iter().map(|n| n.1).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn duplicates() {
+        let c = Coordinates::new(2, vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 5.0, 5.0]);
+        let edges = euclidean_mst(&c);
+
+        assert_eq!(edges.len(), 3);
+        assert_eq!(point_clusters(2, &c).members, vec![vec![0, 1, 2], vec![3]]);
+    }
+}
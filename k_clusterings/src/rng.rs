@@ -0,0 +1,84 @@
+// A small seeded generator (SplitMix64), so randomised clusterings can be repeated exactly.
+// Not for anything that needs real randomness.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng{state: seed}
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = self.state;
+
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Uniform in 0..n.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_f64() * n as f64) as usize
+    }
+
+    // count distinct values from 0..n, by a partial Fisher-Yates shuffle.
+    pub fn sample(&mut self, n: usize, count: usize) -> Vec<usize> {
+        let mut values: Vec<usize> = (0..n).collect();
+        let count = std::cmp::min(count, n);
+
+        for i in 0..count {
+            let j = i + self.below(n - i);
+
+            values.swap(i, j);
+        }
+
+        values.truncate(count);
+
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeatable() {
+        let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+
+        assert!(Rng::new(1).next_u64() != Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn ranges() {
+        let mut rng = Rng::new(7);
+
+        for _ in 0..1000 {
+            let x = rng.next_f64();
+
+            assert!(x >= 0.0 && x < 1.0);
+            assert!(rng.below(5) < 5);
+        }
+
+        let mut s = rng.sample(10, 4);
+
+        s.sort();
+        s.dedup();
+        assert_eq!(s.len(), 4);
+        assert_eq!(rng.sample(3, 10).len(), 3);
+    }
+}
@@ -0,0 +1,188 @@
+use dendrogram::{Cut, Dendrogram};
+use union_find::UnionFind;
+
+// How to pick the cut of a dendrogram when k is not known up front.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    // The cut just below the biggest jump between consecutive merge heights.
+    LargestGap,
+    // The most clusters whose spacing is at least the threshold.
+    MinSpacing(f64),
+    // The most clusters that all have at least this many members.
+    MinClusterSize(usize),
+    // The cut that holds over the widest range of thresholds relative to its own height, so
+    // a jump from 1 to 10 counts as much as one from 100 to 1000. The gap is divided by the
+    // larger of the two heights in absolute value, which keeps negative costs finite.
+    Stability,
+}
+
+// The chosen cut and the evidence for it: a score for every k the strategy looked at. For
+// LargestGap and Stability it is the gap or relative gap above that cut, for MinSpacing the
+// spacing and for MinClusterSize the number of clusters that are too small. Only
+// MinClusterSize can fail, when even the coarsest cut leaves a cluster too small.
+#[derive(Debug, Clone)]
+pub struct Choice {
+    pub k: usize,
+    pub spacing: Option<f64>,
+    pub scores: Vec<(usize, f64)>,
+    pub clustering: Cut,
+}
+
+pub fn choose_k(d: &Dendrogram, strategy: Strategy) -> Result<Choice, &'static str> {
+    let n = d.leaves();
+    let heights: Vec<f64> = d.merges().iter().map(|m| m.height).collect();
+    let mut scores = vec![];
+
+    // The number of merges to apply.
+    let count = match strategy {
+        Strategy::LargestGap | Strategy::Stability => {
+            let mut best: Option<(f64, usize)> = None;
+
+            for c in 1..heights.len() {
+                let (low, high) = (heights[c - 1], heights[c]);
+                let score = if strategy == Strategy::LargestGap {
+                    high - low
+                } else {
+                    let scale = f64::max(high.abs(), low.abs());
+
+                    if scale == 0.0 { 0.0 } else { (high - low) / scale }
+                };
+
+                scores.push((n - c, score));
+
+                // Ties go to the fewer clusters.
+                if best.map_or(true, |(s, _)| score >= s) {
+                    best = Some((score, c));
+                }
+            }
+
+            best.map_or(heights.len(), |(_, c)| c)
+        },
+        Strategy::MinSpacing(threshold) => {
+            for c in 0..heights.len() {
+                scores.push((n - c, heights[c]));
+            }
+
+            heights.iter().take_while(|&&h| h < threshold).count()
+        },
+        Strategy::MinClusterSize(size) => {
+            let mut clusters = UnionFind::new(n);
+            let mut small = if size > 1 { n } else { 0 };
+            let mut count = None;
+
+            for (c, m) in d.merges().iter().enumerate() {
+                scores.push((n - c, small as f64));
+
+                if small == 0 {
+                    count = Some(c);
+                    break;
+                }
+
+                for &x in &[m.edge.0, m.edge.1] {
+                    if clusters.size(x) < size {
+                        small -= 1;
+                    }
+                }
+
+                clusters.union(m.edge.0, m.edge.1);
+
+                if clusters.size(m.edge.0) < size {
+                    small += 1;
+                }
+            }
+
+            match count {
+                Some(c) => c,
+                None if small == 0 => heights.len(),
+                None => return Err("No cut leaves every cluster large enough"),
+            }
+        },
+    };
+
+    let clustering = d.cut(n - count);
+
+    Ok(Choice{k: n - count, spacing: heights.get(count).cloned(), scores: scores, clustering: clustering})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use {Graph, Edge};
+    use dendrogram::Dendrogram;
+
+    fn dendrogram(size: i32, edges: &[(usize, usize, i32)]) -> Dendrogram {
+        let mut g = Graph::new();
+
+        g.build(size);
+
+        for &(h, t, c) in edges {
+            g.vertices[h].edges.push(Edge{head: h, tail: t, cost: c});
+            g.vertices[t].edges.push(Edge{head: t, tail: h, cost: c});
+        }
+
+        Dendrogram::single_linkage(&g)
+    }
+
+    #[test]
+    fn largest_gap() {
+        // Two tight groups of three, far apart.
+        let d = dendrogram(6, &[(0, 1, 1), (1, 2, 2), (3, 4, 1), (4, 5, 3), (2, 3, 20)]);
+        let c = choose_k(&d, Strategy::LargestGap).ok().unwrap();
+
+        assert_eq!(c.k, 2);
+        assert_eq!(c.spacing, Some(20.0));
+        assert_eq!(c.scores.len(), 4);
+        assert!(c.scores.contains(&(2, 17.0)));
+        assert_eq!(c.clustering.members, vec![vec![0, 1, 2], vec![3, 4, 5]]);
+    }
+
+    #[test]
+    fn stability() {
+        // In absolute terms 9 -> 40 is the biggest jump, relatively 0 -> 9 is.
+        let d = dendrogram(4, &[(0, 1, 0), (1, 2, 9), (2, 3, 40)]);
+
+        assert_eq!(choose_k(&d, Strategy::LargestGap).ok().unwrap().k, 2);
+        assert_eq!(choose_k(&d, Strategy::Stability).ok().unwrap().k, 3);
+
+        // Negative costs stay finite, where a plain ratio would divide by zero around -1.
+        let d = dendrogram(4, &[(0, 1, -5), (1, 2, -1), (2, 3, 30)]);
+        let c = choose_k(&d, Strategy::Stability).ok().unwrap();
+
+        assert!(c.scores.iter().all(|&(_, s)| s.is_finite()));
+        assert_eq!(c.k, 2);
+    }
+
+    #[test]
+    fn min_spacing() {
+        let p = Path::new("clustering1.txt");
+        let g = Graph::from_file(p).ok().unwrap();
+        let d = Dendrogram::single_linkage(&g);
+        let c = choose_k(&d, Strategy::MinSpacing(106.0)).ok().unwrap();
+
+        assert!(c.k >= 4);
+        assert!(c.spacing.unwrap() >= 106.0);
+        assert!(choose_k(&d, Strategy::MinSpacing(107.0)).ok().unwrap().k < c.k);
+    }
+
+    #[test]
+    fn min_cluster_size() {
+        let d = dendrogram(6, &[(0, 1, 1), (1, 2, 2), (3, 4, 1), (4, 5, 3), (2, 3, 20)]);
+
+        // Vertex 5 only joins at height 3, after {0, 1, 2} has formed.
+        assert_eq!(choose_k(&d, Strategy::MinClusterSize(1)).ok().unwrap().k, 6);
+        assert_eq!(choose_k(&d, Strategy::MinClusterSize(2)).ok().unwrap().k, 2);
+        assert_eq!(choose_k(&d, Strategy::MinClusterSize(3)).ok().unwrap().k, 2);
+
+        let c = choose_k(&d, Strategy::MinClusterSize(4)).ok().unwrap();
+
+        assert_eq!(c.k, 1);
+        assert_eq!(c.spacing, None);
+        assert!(choose_k(&d, Strategy::MinClusterSize(7)).is_err());
+
+        // A vertex on its own never gets large enough.
+        let d = dendrogram(4, &[(0, 1, 1), (1, 2, 2)]);
+
+        assert!(choose_k(&d, Strategy::MinClusterSize(2)).is_err());
+    }
+}
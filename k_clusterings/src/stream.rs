@@ -0,0 +1,362 @@
+use std::path::{Path, PathBuf};
+use std::io::{BufReader, BufWriter, Error, ErrorKind};
+use std::io::{BufRead, Read, Write};
+use std::fs::File;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::convert::TryFrom;
+use std::sync::atomic::AtomicUsize;
+
+use Clustering;
+use union_find::UnionFind;
+
+// An undirected edge held once, in 12 bytes. Ordered by cost, then by its vertices, so
+// sorting is deterministic however the edges arrive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CompactEdge {
+    pub cost: i32,
+    pub head: u32,
+    pub tail: u32,
+}
+
+impl CompactEdge {
+    fn write<W: Write>(&self, w: &mut W) -> Result<(), Error> {
+        let mut bytes = [0u8; 12];
+
+        bytes[0..4].copy_from_slice(&self.cost.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.head.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.tail.to_le_bytes());
+
+        w.write_all(&bytes)
+    }
+
+    // None at the end of the input.
+    fn read<R: Read>(r: &mut R) -> Result<Option<CompactEdge>, Error> {
+        let mut bytes = [0u8; 12];
+        let mut filled = 0;
+
+        while filled < 12 {
+            match try!(r.read(&mut bytes[filled..])) {
+                0 if filled == 0 => return Ok(None),
+                0 => return Err(Error::new(ErrorKind::UnexpectedEof, "Truncated edge")),
+                count => filled += count,
+            }
+        }
+
+        let word = |i: usize| [bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]];
+
+        Ok(Some(CompactEdge{
+            cost: i32::from_le_bytes(word(0)),
+            head: u32::from_le_bytes(word(4)),
+            tail: u32::from_le_bytes(word(8)),
+        }))
+    }
+}
+
+// Reads the clustering format a line at a time: the number of vertices, then "head tail
+// cost" per edge, numbered from 1. Each edge is handed over as it is read.
+fn read_edges<F>(path: &Path, mut each: F) -> Result<usize, Error>
+    where F: FnMut(CompactEdge) -> Result<(), Error> {
+    let file = try!(File::open(path));
+    let mut buffer = BufReader::new(&file);
+    let mut first_line = String::new();
+
+    try!(buffer.read_line(&mut first_line));
+
+    let vertices = match first_line.trim().parse::<usize>() {
+        Ok(v) => v,
+        Err(_) => return Err(Error::new(ErrorKind::InvalidData, "Invalid header")),
+    };
+
+    for l in buffer.lines() {
+        let line = try!(l);
+        let invalid = || Error::new(ErrorKind::InvalidData, "Invalid edge");
+        let mut details: Vec<i64> = vec![];
+
+        for d in line.split_whitespace() {
+            details.push(try!(d.parse().map_err(|_| invalid())));
+        }
+
+        if details.is_empty() {
+            continue;
+        }
+
+        if details.len() != 3 || details[0] < 1 || details[1] < 1
+            || details[0] as usize > vertices || details[1] as usize > vertices {
+            return Err(invalid());
+        }
+
+        let head = try!(u32::try_from(details[0] - 1).map_err(|_| invalid()));
+        let tail = try!(u32::try_from(details[1] - 1).map_err(|_| invalid()));
+        let cost = try!(i32::try_from(details[2]).map_err(|_| invalid()));
+
+        try!(each(CompactEdge{cost: cost, head: head, tail: tail}));
+    }
+
+    Ok(vertices)
+}
+
+// Every edge of a file, once each and sorted, in one array.
+#[derive(Debug, Clone)]
+pub struct EdgeList {
+    pub vertices: usize,
+    pub edges: Vec<CompactEdge>,
+}
+
+impl EdgeList {
+    pub fn from_file(path: &Path) -> Result<EdgeList, Error> {
+        let mut edges = vec![];
+        let vertices = try!(read_edges(path, |e| { edges.push(e); Ok(()) }));
+
+        edges.sort();
+
+        Ok(EdgeList{vertices: vertices, edges: edges})
+    }
+}
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+// An edge file sorted in runs of at most run_size edges, each written to a scratch file,
+// for files too large to sort in memory. The runs are removed when this is dropped.
+#[derive(Debug)]
+pub struct ExternalSort {
+    pub vertices: usize,
+    runs: Vec<PathBuf>,
+}
+
+impl ExternalSort {
+    pub fn new(path: &Path, run_size: usize, scratch: &Path) -> Result<ExternalSort, Error> {
+        let mut sort = ExternalSort{vertices: 0, runs: vec![]};
+        let mut run: Vec<CompactEdge> = Vec::with_capacity(run_size);
+
+        sort.vertices = try!(read_edges(path, |e| {
+            run.push(e);
+
+            if run.len() >= run_size {
+                try!(sort.spill(&mut run, scratch));
+            }
+
+            Ok(())
+        }));
+
+        try!(sort.spill(&mut run, scratch));
+
+        Ok(sort)
+    }
+
+    fn spill(&mut self, run: &mut Vec<CompactEdge>, scratch: &Path) -> Result<(), Error> {
+        if run.is_empty() {
+            return Ok(());
+        }
+
+        run.sort();
+
+        let id = RUNS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let path = scratch.join(format!("k_clusterings-{}-{}.run", std::process::id(), id));
+
+        // Recorded first, so a failed write is still cleaned up.
+        self.runs.push(path.clone());
+
+        let mut w = BufWriter::new(try!(File::create(&path)));
+
+        for e in run.iter() {
+            try!(e.write(&mut w));
+        }
+
+        try!(w.flush());
+        run.clear();
+
+        Ok(())
+    }
+
+    pub fn runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    // The edges of all runs merged back into one sorted stream.
+    pub fn edges(&self) -> Result<SortedEdges, Error> {
+        let mut merge = SortedEdges{readers: vec![], heap: BinaryHeap::new()};
+
+        for (i, path) in self.runs.iter().enumerate() {
+            let mut r = BufReader::new(try!(File::open(path)));
+
+            if let Some(e) = try!(CompactEdge::read(&mut r)) {
+                merge.heap.push(Head{edge: e, run: i});
+            }
+
+            merge.readers.push(r);
+        }
+
+        Ok(merge)
+    }
+}
+
+impl Drop for ExternalSort {
+    fn drop(&mut self) {
+        for path in &self.runs {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+// The smallest unread edge of a run. Ordering is reversed to make BinaryHeap a min-heap.
+#[derive(Debug, PartialEq, Eq)]
+struct Head {
+    edge: CompactEdge,
+    run: usize,
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.edge.cmp(&self.edge).then_with(|| other.run.cmp(&self.run))
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub struct SortedEdges {
+    readers: Vec<BufReader<File>>,
+    heap: BinaryHeap<Head>,
+}
+
+impl Iterator for SortedEdges {
+    type Item = Result<CompactEdge, Error>;
+
+    fn next(&mut self) -> Option<Result<CompactEdge, Error>> {
+        let head = match self.heap.pop() {
+            Some(head) => head,
+            None => return None,
+        };
+
+        match CompactEdge::read(&mut self.readers[head.run]) {
+            Ok(Some(e)) => self.heap.push(Head{edge: e, run: head.run}),
+            Ok(None) => {},
+            Err(e) => return Some(Err(e)),
+        }
+
+        Some(Ok(head.edge))
+    }
+}
+
+// Kruskal's merge as in cluster_spacing, over edges that arrive already sorted. Only the
+// union-find is held in memory.
+pub fn stream_spacing<I>(groups: usize, vertices: usize, edges: I) -> Result<Clustering, Error>
+    where I: Iterator<Item = Result<CompactEdge, Error>> {
+    let mut clusters = UnionFind::new(vertices);
+    let mut merged = vec![];
+    let mut spacing = 0;
+
+    for e in edges {
+        let e = try!(e);
+        let (head, tail) = (e.head as usize, e.tail as usize);
+
+        if !clusters.connected(head, tail) {
+            if clusters.components() <= groups {
+                spacing = e.cost;
+                break;
+            }
+
+            clusters.union(head, tail);
+            merged.push((head, tail, e.cost));
+        }
+    }
+
+    Ok(Clustering::new(&mut clusters, spacing, merged))
+}
+
+// Clusters an edge file without building a Graph, holding at most run_size edges in memory
+// at once. Runs go to the system's temporary directory.
+pub fn cluster_file(groups: usize, path: &Path, run_size: usize) -> Result<Clustering, Error> {
+    let sort = try!(ExternalSort::new(path, run_size, &std::env::temp_dir()));
+    let edges = try!(sort.edges());
+
+    stream_spacing(groups, sort.vertices, edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use {Graph, cluster_spacing};
+
+    #[test]
+    fn representation() {
+        let list = EdgeList::from_file(Path::new("clustering_small1.txt")).ok().unwrap();
+
+        assert_eq!(list.vertices, 5);
+        assert_eq!(list.edges.len(), 10);
+        assert_eq!(list.edges[0], CompactEdge{cost: 1, head: 0, tail: 1});
+        assert!(list.edges.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn invalid_lines() {
+        let p = std::env::temp_dir().join(format!("k_clusterings-{}-invalid.txt", std::process::id()));
+
+        for contents in &["3\n1 2 5\n2 x 4\n", "3\n1 2 5 7\n", "3\n1 4 5\n", "3\n1 2 3000000000\n"] {
+            std::fs::write(&p, contents).ok().unwrap();
+
+            let e = EdgeList::from_file(&p).err().unwrap();
+
+            assert_eq!(e.kind(), ErrorKind::InvalidData);
+        }
+
+        std::fs::write(&p, "3\n1 2 -5\n\n2 3 2147483647\n").ok().unwrap();
+        assert_eq!(EdgeList::from_file(&p).ok().unwrap().edges.len(), 2);
+
+        std::fs::remove_file(&p).ok().unwrap();
+    }
+
+    #[test]
+    fn round_trip() {
+        let e = CompactEdge{cost: -7, head: 3, tail: 4000000000};
+        let mut bytes = vec![];
+
+        e.write(&mut bytes).ok().unwrap();
+
+        let mut r = &bytes[..];
+
+        assert_eq!(CompactEdge::read(&mut r).ok().unwrap(), Some(e));
+        assert_eq!(CompactEdge::read(&mut r).ok().unwrap(), None);
+        assert!(CompactEdge::read(&mut &bytes[..5]).is_err());
+    }
+
+    #[test]
+    fn matches_cluster_spacing() {
+        let p = Path::new("clustering1.txt");
+        let mut g = Graph::from_file(p).ok().unwrap();
+        let expected = cluster_spacing(4, &mut g);
+        let list = EdgeList::from_file(p).ok().unwrap();
+        let c = stream_spacing(4, list.vertices, list.edges.iter().map(|&e| Ok(e))).ok().unwrap();
+
+        assert_eq!(list.edges.len(), 124750);
+        assert_eq!(c.spacing, 106);
+        assert_eq!(c.members, expected.members);
+    }
+
+    #[test]
+    fn external() {
+        let p = Path::new("clustering1.txt");
+        let sort = ExternalSort::new(p, 10000, &std::env::temp_dir()).ok().unwrap();
+        let runs: Vec<PathBuf> = sort.runs.clone();
+
+        assert_eq!(sort.runs(), 13);
+
+        let edges: Vec<CompactEdge> = sort.edges().ok().unwrap().map(|e| e.ok().unwrap()).collect();
+
+        assert_eq!(edges, EdgeList::from_file(p).ok().unwrap().edges);
+
+        drop(sort);
+        assert!(runs.iter().all(|r| !r.exists()));
+
+        assert_eq!(cluster_file(4, p, 10000).ok().unwrap().spacing, 106);
+
+        for &(file, spacing) in &[("clustering_small2.txt", 8), ("clustering_small3.txt", 7), ("clustering_small4.txt", 17)] {
+            assert_eq!(cluster_file(4, Path::new(file), 3).ok().unwrap().spacing, spacing);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+// Disjoint sets over 0..n with path compression and union by rank. Both together make
+// every operation effectively constant time.
+#[derive(Debug, Clone)]
+pub struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+    size: Vec<usize>,
+    components: usize,
+}
+
+impl UnionFind {
+    pub fn new(n: usize) -> UnionFind {
+        UnionFind{parent: (0..n).collect(), rank: vec![0; n], size: vec![1; n], components: n}
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    // The leader of x's set. Every vertex on the way is pointed straight at it.
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+
+        let mut current = x;
+
+        while self.parent[current] != root {
+            let next = self.parent[current];
+
+            self.parent[current] = root;
+            current = next;
+        }
+
+        root
+    }
+
+    // Merges the sets holding a and b. Returns false if they were already one set.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (ra, rb) = (self.find(a), self.find(b));
+
+        if ra == rb {
+            return false;
+        }
+
+        let (high, low) = if self.rank[ra] >= self.rank[rb] { (ra, rb) } else { (rb, ra) };
+
+        self.parent[low] = high;
+        self.size[high] += self.size[low];
+
+        if self.rank[high] == self.rank[low] {
+            self.rank[high] += 1;
+        }
+
+        self.components -= 1;
+
+        true
+    }
+
+    pub fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    // Number of disjoint sets.
+    pub fn components(&self) -> usize {
+        self.components
+    }
+
+    // Size of the set holding x.
+    pub fn size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+
+        self.size[root]
+    }
+
+    // The leader of every element.
+    pub fn leaders(&mut self) -> Vec<usize> {
+        (0..self.parent.len()).map(|x| self.find(x)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unions() {
+        let mut uf = UnionFind::new(6);
+
+        assert_eq!(uf.components(), 6);
+        assert!(uf.union(0, 1));
+        assert!(uf.union(2, 3));
+        assert!(uf.union(1, 3));
+        assert!(!uf.union(0, 2));
+
+        assert_eq!(uf.components(), 3);
+        assert!(uf.connected(0, 3));
+        assert!(!uf.connected(0, 4));
+        assert_eq!(uf.size(2), 4);
+        assert_eq!(uf.size(5), 1);
+
+        let leaders = uf.leaders();
+
+        assert_eq!(leaders[0], leaders[3]);
+        assert!(leaders[4] != leaders[5]);
+    }
+
+    #[test]
+    fn long_chain() {
+        let n = 100000;
+        let mut uf = UnionFind::new(n);
+
+        for i in 1..n {
+            uf.union(i - 1, i);
+        }
+
+        assert_eq!(uf.components(), 1);
+        assert_eq!(uf.size(0), n);
+        assert!(uf.connected(0, n - 1));
+    }
+}
@@ -41,7 +41,15 @@ pub fn items_from_file(path: &Path) -> Result<Vec<Item>, std::io::Error> {
     Ok(items)
 }
 
-pub fn knapsack(items: &[Item], weight: usize) -> i32 {
+// The items chosen, by index into the input, with their combined weight and value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub items: Vec<usize>,
+    pub weight: usize,
+    pub value: i32,
+}
+
+pub fn knapsack(items: &[Item], weight: usize) -> Selection {
     let mut res: Vec<Vec<i32>> = 
         [vec![0; weight + 1]].iter()
                              .cycle()
@@ -63,7 +71,60 @@ pub fn knapsack(items: &[Item], weight: usize) -> i32 {
         }
     }
 
-    res[items.len()][weight]
+    // Walk back through the table: item i was taken wherever including it changed the value.
+    let mut chosen = vec![];
+    let mut w = weight;
+
+    for i in (0..items.len()).rev() {
+        if res[i+1][w] != res[i][w] {
+            chosen.push(i);
+            w -= items[i].weight;
+        }
+    }
+
+    chosen.reverse();
+
+    Selection{
+        weight: chosen.iter().map(|&i| items[i].weight).sum(),
+        items: chosen,
+        value: res[items.len()][weight],
+    }
+}
+
+// Checks that a selection names each item at most once, that its totals are right and that
+// it fits the capacity.
+pub fn validate(items: &[Item], selection: &Selection, capacity: usize) -> Result<(), &'static str> {
+    let mut seen = vec![false; items.len()];
+    let mut weight = 0;
+    let mut value = 0;
+
+    for &i in &selection.items {
+        if i >= items.len() {
+            return Err("No such item");
+        }
+
+        if seen[i] {
+            return Err("Item chosen twice");
+        }
+
+        seen[i] = true;
+        weight += items[i].weight;
+        value += items[i].value;
+    }
+
+    if weight != selection.weight {
+        return Err("Wrong total weight");
+    }
+
+    if value != selection.value {
+        return Err("Wrong total value");
+    }
+
+    if weight > capacity {
+        return Err("Over capacity");
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -86,23 +147,32 @@ mod tests {
     #[test]
     fn knapsack1() {
         let items: Vec<Item> =
-            vec![(4, 1), (2, 1), (6, 2), (7, 3)].iter()
-                                                .map(|&(v, w)| Item::new(v, w))
-                                                .collect();
+            [(4, 1), (2, 1), (6, 2), (7, 3)].iter()
+                                            .map(|&(v, w)| Item::new(v, w))
+                                            .collect();
+
+        let s = knapsack(&items[..], 5);
 
-        assert_eq!(knapsack(&items[..], 5), 13);
+        assert_eq!(s.value, 13);
+        assert_eq!(s.items, vec![0, 1, 3]);
+        assert_eq!(s.weight, 5);
+        assert!(validate(&items[..], &s, 5).is_ok());
     }
 
     #[test]
     fn knapsack2() {
         let items: Vec<Item> =
-            vec![(7, 5), (2, 5), (7, 7), (9, 3),
-                 (5, 9), (4, 1), (2, 8), (6, 8),
-                 (1, 9), (7, 7)].iter()
-                                .map(|&(v, w)| Item::new(v, w))
-                                .collect();
+            [(7, 5), (2, 5), (7, 7), (9, 3),
+             (5, 9), (4, 1), (2, 8), (6, 8),
+             (1, 9), (7, 7)].iter()
+                            .map(|&(v, w)| Item::new(v, w))
+                            .collect();
+
+        let s = knapsack(&items[..], 40);
 
-        assert_eq!(knapsack(&items[..], 40), 45);
+        assert_eq!(s.value, 45);
+        assert!(s.weight <= 40);
+        assert!(validate(&items[..], &s, 40).is_ok());
     }
 
     #[test]
@@ -110,6 +180,25 @@ mod tests {
         let p = Path::new("knapsack1.txt");
         let items: Vec<Item> = items_from_file(p).ok().unwrap();
 
-        assert_eq!(knapsack(&items[..], 10000), 2493893);
+        let s = knapsack(&items[..], 10000);
+
+        assert_eq!(s.value, 2493893);
+        assert!(validate(&items[..], &s, 10000).is_ok());
+    }
+
+    #[test]
+    fn validation() {
+        let items: Vec<Item> =
+            [(4, 1), (2, 1), (6, 2), (7, 3)].iter()
+                                            .map(|&(v, w)| Item::new(v, w))
+                                            .collect();
+        let s = Selection{items: vec![0, 2, 3], weight: 6, value: 17};
+
+        assert!(validate(&items[..], &s, 6).is_ok());
+        assert_eq!(validate(&items[..], &s, 5), Err("Over capacity"));
+        assert_eq!(validate(&items[..], &Selection{items: vec![0, 0], weight: 2, value: 8}, 5), Err("Item chosen twice"));
+        assert_eq!(validate(&items[..], &Selection{items: vec![4], weight: 0, value: 0}, 5), Err("No such item"));
+        assert_eq!(validate(&items[..], &Selection{items: vec![1], weight: 1, value: 4}, 5), Err("Wrong total value"));
+        assert_eq!(validate(&items[..], &Selection{items: vec![], weight: 0, value: 0}, 0), Ok(()));
     }
 }